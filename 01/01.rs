@@ -1,6 +1,8 @@
-use std::io::{self, BufRead};
+use aoc2019::input::{self, InputError};
+use aoc2019::output::{self, answer};
+use std::io::BufRead;
 
-fn part1(modules: &Vec<i64>) -> i64 {
+fn part1(modules: &[i64]) -> i64 {
     modules.iter().map(|mass| mass / 3 - 2).sum()
 }
 
@@ -22,15 +24,14 @@ fn part2(mut modules: Vec<i64>) -> i64 {
     total
 }
 
-fn main() -> Result<(), ()> {
-    let modules: Vec<i64> = std::io::stdin()
-        .lock()
+fn main() -> Result<(), InputError> {
+    let modules: Vec<i64> = input::reader(output::args().nth(1))?
         .lines()
         .map(|mass| mass.unwrap().parse::<i64>().unwrap())
         .collect();
 
-    dbg!(part1(&modules));
-    dbg!(part2(modules));
+    answer(1, part1(&modules));
+    answer(2, part2(modules));
 
     Ok(())
 }
@@ -1,27 +1,12 @@
-use std::io::{self, BufRead};
-use std::time::Instant;
-
-fn run(mem: &mut Vec<i64>) {
-    let mut ip = 0;
-    loop {
-        let opcode = mem[ip];
-        let left = mem[ip + 1] as usize;
-        let right = mem[ip + 2] as usize;
-        let to = mem[ip + 3] as usize;
-        match opcode {
-            1 => {
-                mem[to] = mem[left] + mem[right];
-                ip += 4;
-            }
-
-            2 => {
-                mem[to] = mem[left] * mem[right];
-                ip += 4;
-            }
+use aoc2019::input::{self, InputError};
+use aoc2019::intcode::interpreter::Interpreter;
+use aoc2019::intcode::Word;
+use aoc2019::output::{self, answer};
 
-            _ => break,
-        }
-    }
+fn run(mem: &[Word]) -> Word {
+    let mut interpreter = Interpreter::new(mem);
+    interpreter.run().unwrap();
+    interpreter.read(0)
 }
 
 fn part1(mem: &Vec<i64>) -> i64 {
@@ -29,8 +14,7 @@ fn part1(mem: &Vec<i64>) -> i64 {
     mem[1] = 12;
     mem[2] = 2;
 
-    run(&mut mem);
-    mem[0]
+    run(&mem)
 }
 
 fn part2(mem: &Vec<i64>) -> i64 {
@@ -39,9 +23,7 @@ fn part2(mem: &Vec<i64>) -> i64 {
             let mut mem = (*mem).clone();
             mem[1] = noun;
             mem[2] = verb;
-            run(&mut mem);
-
-            if mem[0] == 19690720 {
+            if run(&mem) == 19690720 {
                 return 100 * noun + verb;
             }
         }
@@ -49,17 +31,99 @@ fn part2(mem: &Vec<i64>) -> i64 {
     panic!("no solution found");
 }
 
-fn main() -> Result<(), ()> {
-    let mut input = String::new();
-    std::io::stdin().lock().read_line(&mut input).unwrap();
+fn main() -> Result<(), InputError> {
+    let items = input::read_program(output::args().nth(1))?;
 
-    let items: Vec<i64> = input
-        .split(",")
-        .map(|x| x.trim().parse().unwrap())
-        .collect();
-
-    dbg!(part1(&items));
-    dbg!(part2(&items));
+    answer(1, part1(&items));
+    answer(2, part2(&items));
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Day 02's old add/multiply-only VM, wrapping on overflow like
+    // the interpreter does.
+    fn minimal_run(mem: &mut [Word]) {
+        let mut ip = 0;
+        while mem[ip] != 99 {
+            let opcode = mem[ip];
+            let left = mem[ip + 1] as usize;
+            let right = mem[ip + 2] as usize;
+            let to = mem[ip + 3] as usize;
+            match opcode {
+                1 => {
+                    mem[to] = mem[left].wrapping_add(mem[right]);
+                    ip += 4;
+                }
+
+                2 => {
+                    mem[to] = mem[left].wrapping_mul(mem[right]);
+                    ip += 4;
+                }
+
+                _ => panic!("invalid opcode {}", opcode),
+            }
+        }
+    }
+
+    // A linear congruential generator, so every run tries the same programs.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 = self
+                .0
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (self.0 >> 33) as usize % n
+        }
+    }
+
+    // Some adds and multiplies, a halt, and then data. Instructions read from
+    // anywhere but only write to the data, so they can't turn into other
+    // opcodes.
+    fn random_program(rng: &mut Rng) -> Vec<Word> {
+        let instructions = 1 + rng.below(10);
+        let data = 1 + rng.below(10);
+        let code = instructions * 4 + 1;
+        let len = code + data;
+
+        let mut program = Vec::with_capacity(len);
+        for _ in 0..instructions {
+            program.push(1 + rng.below(2) as Word);
+            program.push(rng.below(len) as Word);
+            program.push(rng.below(len) as Word);
+            program.push((code + rng.below(data)) as Word);
+        }
+        program.push(99);
+        for _ in 0..data {
+            program.push(rng.below(100) as Word);
+        }
+        program
+    }
+
+    #[test]
+    fn example_programs() {
+        assert_eq!(run(&[1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]), 3500);
+        assert_eq!(run(&[1, 0, 0, 0, 99]), 2);
+        assert_eq!(run(&[1, 1, 1, 4, 99, 5, 6, 0, 99]), 30);
+    }
+
+    #[test]
+    fn minimal_vm_matches_interpreter() {
+        let mut rng = Rng(2019);
+        for _ in 0..1000 {
+            let program = random_program(&mut rng);
+
+            let mut expected = program.clone();
+            minimal_run(&mut expected);
+
+            let mut interpreter = Interpreter::new(&program);
+            interpreter.run().unwrap();
+            assert_eq!(interpreter.memory(), &expected, "{:?}", program);
+        }
+    }
+}
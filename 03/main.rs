@@ -1,3 +1,6 @@
+use aoc2019::input::{self, InputError};
+use aoc2019::output::{self, answer};
+use aoc2019::sayln;
 use ordered_float::NotNan;
 use std::collections::BinaryHeap;
 use std::io::BufRead;
@@ -29,7 +32,7 @@ fn line_intersection(a: &(Point, Point), b: &(Point, Point)) -> Option<Point> {
 
     let t = ((a1.1 - b1.1) * (b2.0 - b1.0) - (a1.0 - b1.0) * (b2.1 - b1.1)) / denom;
     let u = ((a1.1 - b1.1) * (a2.0 - a1.0) - (a1.0 - b1.0) * (a2.1 - a1.1)) / denom;
-    if t >= 0.0 && t <= 1.0 && u >= 0.0 && u <= 1.0 {
+    if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) {
         Some(Point(a1.0 + t * (a2.0 - a1.0), a1.1 + t * (a2.1 - a1.1)))
     } else {
         None
@@ -58,11 +61,25 @@ fn to_lines(wire: &[(Direction, f32)]) -> Vec<(Point, Point)> {
     lines
 }
 
-fn run(items: &[Vec<(Direction, f32)>]) -> (f32, f32) {
+// Points where a wire crosses itself. Consecutive lines always meet at their
+// shared corner, so those pairs are skipped.
+fn self_crossings(wire: &[(Point, Point)]) -> Vec<Point> {
+    let mut crossings = Vec::new();
+    for (i, a) in wire.iter().enumerate() {
+        for b in wire.iter().skip(i + 2) {
+            if let Some(point) = line_intersection(a, b) {
+                crossings.push(point);
+            }
+        }
+    }
+    crossings
+}
+
+fn run(items: &[Vec<(Direction, f32)>]) -> (f32, Point, f32) {
     let wire1 = to_lines(&items[0]);
     let wire2 = to_lines(&items[1]);
 
-    let mut manhattans = BinaryHeap::new();
+    let mut closest: Option<(f32, Point)> = None;
     let mut lengths = BinaryHeap::new();
 
     let mut a_length = 0.0;
@@ -73,14 +90,14 @@ fn run(items: &[Vec<(Direction, f32)>]) -> (f32, f32) {
         for b in &wire2 {
             b_length += line_length(b.0, b.1);
 
-            let point = match line_intersection(&a, &b) {
+            let point = match line_intersection(a, b) {
                 None => continue,
                 Some(point) => point,
             };
 
             let manhattan = point.manhattan();
-            if manhattan != 0.0 {
-                manhattans.push(-NotNan::new(manhattan).unwrap());
+            if manhattan != 0.0 && closest.is_none_or(|(closest, _)| manhattan < closest) {
+                closest = Some((manhattan, point));
             }
 
             let length =
@@ -91,35 +108,62 @@ fn run(items: &[Vec<(Direction, f32)>]) -> (f32, f32) {
         }
     }
 
-    (
-        -manhattans.peek().unwrap().into_inner(),
-        -lengths.peek().unwrap().into_inner(),
-    )
+    let (manhattan, crossing) = closest.unwrap();
+    (manhattan, crossing, -lengths.peek().unwrap().into_inner())
 }
 
-fn main() {
+fn parse_wire(wire: &str) -> Vec<(Direction, f32)> {
+    wire.split(',')
+        .map(|command| {
+            let (direction, amount) = command.trim().split_at(1);
+            let amount: f32 = amount.parse().unwrap();
+            match direction {
+                "U" => (Direction::Y, amount),
+                "D" => (Direction::Y, -amount),
+                "R" => (Direction::X, amount),
+                "L" => (Direction::X, -amount),
+                _ => panic!(),
+            }
+        })
+        .collect()
+}
+
+fn main() -> Result<(), InputError> {
     let now = Instant::now();
-    let items: Vec<Vec<(Direction, f32)>> = std::io::stdin()
-        .lock()
+    let items: Vec<Vec<(Direction, f32)>> = input::reader(output::args().nth(1))?
         .lines()
-        .map(|wire| {
-            wire.unwrap()
-                .split(',')
-                .map(|command| {
-                    let (direction, amount) = command.trim().split_at(1);
-                    let amount: f32 = amount.parse().unwrap();
-                    match direction {
-                        "U" => (Direction::Y, amount),
-                        "D" => (Direction::Y, -amount),
-                        "R" => (Direction::X, amount),
-                        "L" => (Direction::X, -amount),
-                        _ => panic!(),
-                    }
-                })
-                .collect()
-        })
+        .map(|wire| parse_wire(&wire.unwrap()))
         .collect();
 
-    let (part1, part2) = run(&items);
-    dbg!(part1, part2, now.elapsed());
+    for (i, wire) in items.iter().enumerate() {
+        let crossings = self_crossings(&to_lines(wire));
+        if !crossings.is_empty() {
+            sayln!("wire {} crosses itself at {:?}", i + 1, crossings);
+        }
+    }
+
+    let (part1, crossing, part2) = run(&items);
+    answer(1, part1);
+    answer(2, part2);
+    sayln!("crossing: {:?}, {:?}", crossing, now.elapsed());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closest_crossing() {
+        let wires = vec![parse_wire("R8,U5,L5,D3"), parse_wire("U7,R6,D4,L4")];
+        assert_eq!(run(&wires), (6.0, Point(3.0, 3.0), 30.0));
+    }
+
+    #[test]
+    fn wire_crossing_itself() {
+        let wire = to_lines(&parse_wire("R8,U5,L5,D8"));
+        assert_eq!(self_crossings(&wire), vec![Point(3.0, 0.0)]);
+        assert_eq!(self_crossings(&wire[..3]), vec![]);
+    }
 }
@@ -1,10 +1,106 @@
+use aoc2019::input::{self, InputError};
+use aoc2019::output::{self, answer};
+use aoc2019::sayln;
+use std::fmt::{Debug, Display, Formatter};
+use std::io::Read;
+use std::thread;
 use std::time::Instant;
 
-fn filter(mut p: u32) -> bool {
+const THREADS: u32 = 8;
+
+// Passwords are six digits long.
+const LOWEST: u32 = 100000;
+const HIGHEST: u32 = 999999;
+
+enum RangeError {
+    Input(InputError),
+    Malformed(String),
+    Reversed { low: u32, high: u32 },
+    OutOfRange(u32),
+}
+
+impl From<InputError> for RangeError {
+    fn from(error: InputError) -> Self {
+        RangeError::Input(error)
+    }
+}
+
+impl Display for RangeError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            RangeError::Input(error) => write!(formatter, "{}", error),
+            RangeError::Malformed(input) => write!(formatter, "expected low-high, got {:?}", input),
+            RangeError::Reversed { low, high } => {
+                write!(formatter, "range is reversed: {} is above {}", low, high)
+            }
+            RangeError::OutOfRange(value) => write!(
+                formatter,
+                "{} is not between {} and {}",
+                value, LOWEST, HIGHEST
+            ),
+        }
+    }
+}
+
+impl Debug for RangeError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        Display::fmt(self, formatter)
+    }
+}
+
+// Parses a `low-high` puzzle input.
+fn parse_range(input: &str) -> Result<(u32, u32), RangeError> {
+    let malformed = || RangeError::Malformed(input.to_string());
+    let mut parts = input.trim().split('-');
+    let mut bound = || -> Result<u32, RangeError> {
+        parts
+            .next()
+            .ok_or_else(malformed)?
+            .parse()
+            .map_err(|_| malformed())
+    };
+    let low = bound()?;
+    let high = bound()?;
+    if parts.next().is_some() {
+        return Err(malformed());
+    }
+
+    for value in &[low, high] {
+        if *value < LOWEST || *value > HIGHEST {
+            return Err(RangeError::OutOfRange(*value));
+        }
+    }
+    if low > high {
+        return Err(RangeError::Reversed { low, high });
+    }
+
+    Ok((low, high))
+}
+
+fn filter_part1(mut p: u32) -> bool {
+    let mut double = false;
+
+    for _ in 0..5 {
+        let b = p % 10;
+        let a = p / 10 % 10;
+        if a > b {
+            return false;
+        }
+
+        if a == b {
+            double = true;
+        }
+        p /= 10;
+    }
+
+    double
+}
+
+fn filter_part2(mut p: u32) -> bool {
     let mut double = 0;
     let mut prev_double = 0;
 
-    for i in 0..5 {
+    for _ in 0..5 {
         let b = p % 10;
         let a = p / 10 % 10;
         if a > b {
@@ -19,25 +115,89 @@ fn filter(mut p: u32) -> bool {
                 double = a
             }
         }
-        p = p / 10;
+        p /= 10;
     }
 
     double != 0
 }
 
-fn main() {
+// Both ends are included, like the puzzle's range.
+fn count(from: u32, to: u32, rule: fn(u32) -> bool) -> usize {
+    (from..=to).filter(|p| rule(*p)).count()
+}
+
+// Same as `count`, but splits the range into chunks counted on separate threads.
+fn count_parallel(from: u32, to: u32, rule: fn(u32) -> bool) -> usize {
+    let chunk = (to - from + THREADS) / THREADS;
+    let handles: Vec<_> = (0..THREADS)
+        .map(|i| {
+            let start = from + i * chunk;
+            let end = (start + chunk - 1).min(to);
+            thread::spawn(move || count(start, end, rule))
+        })
+        .collect();
+
+    handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .sum()
+}
+
+// The puzzle input is a single `low-high` line.
+fn main() -> Result<(), RangeError> {
+    let mut range = String::new();
+    input::reader(output::args().nth(1))?
+        .read_to_string(&mut range)
+        .unwrap();
+    let (from, to) = parse_range(&range)?;
+
     let now = Instant::now();
 
-    let from = 100000;
-    let to = 999999;
-    let mut count = 0;
+    let part1 = count_parallel(from, to, filter_part1);
+    let part2 = count_parallel(from, to, filter_part2);
+
+    let elapsed = now.elapsed();
+    answer(1, part1);
+    answer(2, part2);
+    sayln!("{:?}", elapsed);
+
+    Ok(())
+}
 
-    for i in from..to {
-        if filter(i) {
-            count += 1
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parallel_matches_serial() {
+        for rule in &[filter_part1 as fn(u32) -> bool, filter_part2] {
+            assert_eq!(
+                count_parallel(111111, 222222, *rule),
+                count(111111, 222222, *rule)
+            );
         }
     }
 
-    let elapsed = now.elapsed();
-    println!("{} {:?}", count, elapsed);
+    #[test]
+    fn bad_ranges() {
+        assert!(matches!(
+            parse_range("999999-100000"),
+            Err(RangeError::Reversed {
+                low: 999999,
+                high: 100000
+            })
+        ));
+        assert!(matches!(
+            parse_range("99999-100000"),
+            Err(RangeError::OutOfRange(99999))
+        ));
+        assert!(matches!(
+            parse_range("100000"),
+            Err(RangeError::Malformed(_))
+        ));
+        assert!(matches!(
+            parse_range("100000-999999\n"),
+            Ok((100000, 999999))
+        ));
+    }
 }
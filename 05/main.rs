@@ -1,230 +1,71 @@
-use std::fmt::{Display, Formatter};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use text_io::{try_read, try_scan};
-
-fn get(mem: &Vec<i64>, (mode, value): (i64, i64)) -> Option<i64> {
-    match mode {
-        0 => mem.get(value as usize).map(|i| *i),
-        1 => Some(value),
-        _ => panic!("unexpected mode {}", mode),
+use aoc2019::input::{self, InputError};
+use aoc2019::intcode::interpreter::Interpreter;
+use aoc2019::output::{self, answer};
+use aoc2019::sayln;
+
+// Runs the diagnostic program, returning the final diagnostic code. The outputs
+// before it are test results, which are 0 when the test passed.
+fn diagnostic(memory: &[i64], input: i64) -> i64 {
+    let mut interpreter = Interpreter::new(memory);
+    interpreter.echo_output = false;
+    interpreter.push_input(input);
+    interpreter.run().unwrap();
+
+    let code = interpreter.outputs.pop_back().unwrap();
+    if interpreter.outputs.iter().any(|&output| output != 0) {
+        sayln!("failed tests: {:?}", interpreter.outputs);
     }
+    code
 }
 
-fn get_mut(mem: &mut Vec<i64>, (mode, value): (i64, i64)) -> Option<&mut i64> {
-    match mode {
-        0 => mem.get_mut(value as usize),
-        1 => panic!("can't get immediate as mut"),
-        _ => panic!("unexpected mode {}", mode),
-    }
+fn part1(memory: &[i64]) -> i64 {
+    diagnostic(memory, 1)
 }
 
-#[derive(Debug)]
-enum Opcode {
-    Add,
-    Multiply,
-    Halt,
+fn part2(memory: &[i64]) -> i64 {
+    diagnostic(memory, 5)
 }
 
-impl From<i64> for Opcode {
-    fn from(item: i64) -> Self {
-        match item {
-            1 => Opcode::Add,
-            2 => Opcode::Multiply,
-            99 => Opcode::Halt,
-            _ => panic!("unknown instruction {}", item),
-        }
-    }
-}
+fn main() -> Result<(), InputError> {
+    let mem = input::read_program(output::args().nth(1))?;
 
-#[derive(Debug)]
-enum ParameterMode {
-    Position = 0,
-    Immediate = 1,
-}
+    answer(1, part1(&mem));
+    answer(2, part2(&mem));
 
-impl From<i64> for ParameterMode {
-    fn from(item: i64) -> Self {
-        match item {
-            0 => ParameterMode::Position,
-            1 => ParameterMode::Immediate,
-            _ => panic!("unknown parameter mode {}", item),
-        }
-    }
-}
-
-#[derive(Debug)]
-struct Parameter {
-    pub mode: ParameterMode,
-    pub value: i64,
+    Ok(())
 }
 
-impl Parameter {
-    fn new(mode: ParameterMode, value: i64) -> Self {
-        Self { mode, value }
-    }
-
-    fn value(&self, memory: &Vec<i64>) -> Option<i64> {
-        match self.mode {
-            ParameterMode::Position => memory.get(self.value as usize).map(|i| *i),
-            ParameterMode::Immediate => Some(self.value),
-        }
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn value_mut<'a>(&self, memory: &'a mut Vec<i64>) -> Option<&'a mut i64> {
-        match self.mode {
-            ParameterMode::Position => memory.get_mut(self.value as usize),
-            ParameterMode::Immediate => panic!("can't get immediate as mut"),
-        }
-    }
-}
+    #[test]
+    fn mixed_modes() {
+        let mut interpreter = Interpreter::new(&[1002, 4, 3, 4, 33]);
+        interpreter.run().unwrap();
+        assert_eq!(interpreter.memory(), &vec![1002, 4, 3, 4, 99]);
 
-impl Display for Parameter {
-    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
-        write!(formatter, "{:?}:{}", self.mode, self.value)
+        let mut interpreter = Interpreter::new(&[1101, 100, -1, 4, 0]);
+        interpreter.run().unwrap();
+        assert_eq!(interpreter.memory(), &vec![1101, 100, -1, 4, 99]);
     }
-}
-
-#[derive(Debug)]
-struct Instruction {
-    pub opcode: Opcode,
-    pub parameters: (Parameter, Parameter, Parameter),
-}
 
-impl Instruction {
-    pub fn fetch(ip: usize, memory: &mut Vec<i64>) -> Option<Self> {
-        let instruction = memory.get(ip)?;
-
-        let opcode = Opcode::from(instruction % 100);
-        let parameters = (
-            Parameter::new(
-                ParameterMode::from(instruction / 100 % 10),
-                *memory.get(ip + 1).unwrap_or(&0),
-            ),
-            Parameter::new(
-                ParameterMode::from(instruction / 100 % 10),
-                *memory.get(ip + 2).unwrap_or(&0),
-            ),
-            Parameter::new(
-                ParameterMode::from(instruction / 100 % 10),
-                *memory.get(ip + 3).unwrap_or(&0),
-            ),
-        );
-
-        Some(Self { opcode, parameters })
+    #[test]
+    fn compare_to_eight() {
+        let memory = vec![
+            3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0,
+            0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4,
+            20, 1105, 1, 46, 98, 99,
+        ];
+        assert_eq!(diagnostic(&memory, 7), 999);
+        assert_eq!(diagnostic(&memory, 8), 1000);
+        assert_eq!(diagnostic(&memory, 9), 1001);
     }
-}
-
-struct Interpreter {
-    pub memory: Vec<i64>,
-}
 
-impl Interpreter {
-    fn fetch_instruction(&self, ip: usize) -> Option<Instruction> {
-        None
+    #[test]
+    fn jump_test() {
+        let memory = vec![3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9];
+        assert_eq!(diagnostic(&memory, 0), 0);
+        assert_eq!(diagnostic(&memory, 7), 1);
     }
-
-    pub fn run(&mut self) {}
-}
-
-fn run(mem: &mut Vec<i64>) -> Option<()> {
-    let mut ip = 0;
-    println!(
-        " {:<5}  {:<6}  {:<5}  {:<5}  {:<5}",
-        "ip", "opcode", "a", "b", "c"
-    );
-    println!("========================================");
-    loop {
-        let instruction = Instruction::fetch(ip, mem)?;
-        let (a, b, c) = &instruction.parameters;
-
-        println!(
-            " {:<5}  {:?}\t  {}  {}  {}",
-            ip, instruction.opcode, a, b, c,
-        );
-
-        ip = match instruction.opcode {
-            Opcode::Add => {
-                *c.value_mut(mem)? = a.value(mem)? + b.value(mem)?;
-                ip + 4
-            }
-
-            Opcode::Multiply => {
-                *c.value_mut(mem)? = a.value(mem)? * b.value(mem)?;
-                ip + 4
-            }
-
-            Opcode::Halt => {
-                break;
-            } // 3 => {
-              //     // read
-              //     *get_mut(mem, a)? = try_read!().ok()?;
-              //     ip + 2
-              // }
-
-              // 4 => {
-              //     // write
-              //     println!(">> {}", get(mem, a)?);
-              //     ip + 2
-              // }
-
-              // 5 => {
-              //     // jump-if-true
-              //     if get(mem, a)? != 0 {
-              //         get(mem, b)? as usize
-              //     } else {
-              //         ip + 3
-              //     }
-              // }
-
-              // 6 => {
-              //     // jump-if-false
-              //     if get(mem, a)? == 0 {
-              //         get(mem, b)? as usize
-              //     } else {
-              //         ip + 3
-              //     }
-              // }
-
-              // 7 => {
-              //     // less than
-              //     let result = get(mem, a)? < get(mem, b)?;
-              //     *get_mut(mem, c)? = if result { 1 } else { 0 };
-              //     ip + 4
-              // }
-
-              // 8 => {
-              //     // equals
-              //     let result = get(mem, a)? == get(mem, b)?;
-              //     *get_mut(mem, c)? = if result { 1 } else { 0 };
-              //     ip + 4
-              // }
-
-              // 99 => {
-              //     // halt
-              //     break;
-              // }
-
-              // _ => {
-              //     // unknown opcode
-              //     panic!("unknown opcode (instruction={})", instruction)
-              // }
-        }
-    }
-    Some(())
-}
-
-fn main() -> Result<(), ()> {
-    let file = File::open(std::env::args().nth(1).expect("no filename provided")).unwrap();
-    let mut input = String::new();
-    BufReader::new(file).read_line(&mut input).unwrap();
-
-    let mut mem: Vec<i64> = input
-        .split(",")
-        .map(|x| x.trim().parse().unwrap())
-        .collect();
-
-    run(&mut mem);
-
-    Ok(())
 }
@@ -1,16 +1,19 @@
-use std::collections::HashMap;
-use std::io::BufRead;
+use aoc2019::input::{self, InputError};
+use aoc2019::output::{self, answer};
+use aoc2019::sayln;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Display, Formatter};
+use std::io::{BufRead, BufReader};
 use std::time::Instant;
 
-fn main() {
-    let san: usize = usize::from_str_radix("san", 36).unwrap();
-    let you: usize = usize::from_str_radix("you", 36).unwrap();
-    let com: usize = usize::from_str_radix("com", 36).unwrap();
+type Weights = HashMap<(usize, usize), usize>;
+
+// What each object orbits, for when the map is too big for child lists.
+type Parents = HashMap<usize, usize>;
 
-    let stdin = std::io::stdin();
-    let lines = stdin.lock().lines();
+fn read_input(reader: impl BufRead) -> HashMap<usize, Vec<usize>> {
     let mut tree = HashMap::new();
-    for line in lines {
+    for line in reader.lines() {
         let line = line.unwrap();
         let mut parts = line.trim().split(')');
         let from = usize::from_str_radix(parts.next().unwrap(), 36).unwrap();
@@ -19,16 +22,134 @@ fn main() {
         let entry = tree.entry(from).or_insert_with(Vec::new);
         entry.push(to);
     }
-    let now = Instant::now();
+    tree
+}
+
+enum OrbitError {
+    Input(InputError),
+    // The body is listed as orbiting both of these.
+    TwoParents(String, String, String),
+}
+
+impl From<InputError> for OrbitError {
+    fn from(error: InputError) -> Self {
+        OrbitError::Input(error)
+    }
+}
+
+impl Display for OrbitError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            OrbitError::Input(error) => write!(formatter, "{}", error),
+            OrbitError::TwoParents(body, first, second) => {
+                write!(formatter, "{} orbits both {} and {}", body, first, second)
+            }
+        }
+    }
+}
+
+impl Debug for OrbitError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        Display::fmt(self, formatter)
+    }
+}
+
+// Checks that nothing orbits two bodies at once, and returns how many distinct
+// bodies there are.
+fn validate(tree: &HashMap<usize, Vec<usize>>) -> Result<usize, OrbitError> {
+    let mut parents = HashMap::new();
+    for (&from, children) in tree {
+        for &to in children {
+            if let Some(other) = parents.insert(to, from) {
+                return Err(OrbitError::TwoParents(name(to), name(other), name(from)));
+            }
+        }
+    }
+
+    let bodies: HashSet<_> = tree.keys().chain(parents.keys()).collect();
+    Ok(bodies.len())
+}
+
+fn read_parents(reader: impl BufRead) -> Result<Parents, OrbitError> {
+    let mut parents = HashMap::new();
+    for line in reader.lines() {
+        let line = line.unwrap();
+        let mut parts = line.trim().split(')');
+        let from = usize::from_str_radix(parts.next().unwrap(), 36).unwrap();
+        let to = usize::from_str_radix(parts.next().unwrap(), 36).unwrap();
+
+        if let Some(other) = parents.insert(to, from) {
+            return Err(OrbitError::TwoParents(name(to), name(other), name(from)));
+        }
+    }
+    Ok(parents)
+}
+
+// Lines look like `A)B 5`, giving the cost of B's orbit around A.
+fn read_weights(reader: impl BufRead) -> Weights {
+    let mut weights = HashMap::new();
+    for line in reader.lines() {
+        let line = line.unwrap();
+        let mut parts = line.trim().split([')', ' ']);
+        let from = usize::from_str_radix(parts.next().unwrap(), 36).unwrap();
+        let to = usize::from_str_radix(parts.next().unwrap(), 36).unwrap();
+        let weight = parts.next().unwrap().parse().unwrap();
+
+        weights.insert((from, to), weight);
+    }
+    weights
+}
+
+// Turns a base 36 node id back into its name.
+fn name(mut id: usize) -> String {
+    let mut name = Vec::new();
+    while id > 0 {
+        name.push(std::char::from_digit((id % 36) as u32, 36).unwrap());
+        id /= 36;
+    }
+    name.iter().rev().collect::<String>().to_uppercase()
+}
+
+fn to_dot(tree: &HashMap<usize, Vec<usize>>) -> String {
+    let mut edges: Vec<_> = tree
+        .iter()
+        .flat_map(|(from, children)| {
+            children
+                .iter()
+                .map(move |to| format!("    {} -> {};\n", name(*from), name(*to)))
+        })
+        .collect();
+    edges.sort();
+    format!("digraph orbits {{\n{}}}\n", edges.concat())
+}
+
+// Cumulative weight up to the first `len` nodes of a path.
+fn depth(path: &[(usize, usize)], len: usize) -> usize {
+    path[..len].last().map_or(0, |(_, depth)| *depth)
+}
+
+// Returns the total of all direct and indirect orbits, and the transfers needed
+// to get from the object YOU orbits to the one SAN orbits. Orbits missing from
+// `weights` (or all of them, without weights) count as 1.
+fn solve(tree: &HashMap<usize, Vec<usize>>, weights: Option<&Weights>) -> (usize, usize) {
+    let san: usize = usize::from_str_radix("san", 36).unwrap();
+    let you: usize = usize::from_str_radix("you", 36).unwrap();
+    let com: usize = usize::from_str_radix("com", 36).unwrap();
+
+    let weight = |from: usize, to: usize| {
+        weights
+            .and_then(|weights| weights.get(&(from, to)))
+            .copied()
+            .unwrap_or(1)
+    };
 
     let mut orbits = 0;
     let mut santa_path = None;
     let mut you_path = None;
 
     let mut stack = vec![(com, vec![])];
-    while !stack.is_empty() {
-        let (node, path) = stack.pop().unwrap();
-        orbits += path.len();
+    while let Some((node, path)) = stack.pop() {
+        orbits += depth(&path, path.len());
 
         if node == san {
             santa_path = Some(path.clone());
@@ -39,7 +160,7 @@ fn main() {
         if let Some(children) = tree.get(&node) {
             for child in children {
                 let mut new_path = path.clone();
-                new_path.push(child);
+                new_path.push((*child, depth(&path, path.len()) + weight(node, *child)));
                 stack.push((*child, new_path));
             }
         }
@@ -49,14 +170,197 @@ fn main() {
     let you_path = you_path.unwrap();
     let mut lca = 0;
     for i in 0..santa_path.len() {
-        if you_path[i] != santa_path[i] {
+        if you_path[i].0 != santa_path[i].0 {
             lca = i;
             break;
         }
     }
 
-    let transfers = santa_path.len() + you_path.len() - lca * 2 - 2;
+    let transfers = depth(&santa_path, santa_path.len() - 1) + depth(&you_path, you_path.len() - 1)
+        - depth(&santa_path, lca) * 2;
+
+    (orbits, transfers)
+}
+
+// Like solve, but works from what each object orbits. Depths are looked up and
+// remembered as they're needed instead of walking the tree with paths.
+fn solve_parents(parents: &Parents, weights: Option<&Weights>) -> (usize, usize) {
+    let san: usize = usize::from_str_radix("san", 36).unwrap();
+    let you: usize = usize::from_str_radix("you", 36).unwrap();
+
+    let weight = |from: usize, to: usize| {
+        weights
+            .and_then(|weights| weights.get(&(from, to)))
+            .copied()
+            .unwrap_or(1)
+    };
+
+    let mut depths: HashMap<usize, usize> = HashMap::new();
+    let mut depth = |node: usize| {
+        // Climb until a known depth (or COM), then fill in the way back down.
+        let mut unknown = vec![];
+        let mut current = node;
+        let mut known = 0;
+        while let Some(&parent) = parents.get(&current) {
+            if let Some(&depth) = depths.get(&current) {
+                known = depth;
+                break;
+            }
+            unknown.push((parent, current));
+            current = parent;
+        }
+
+        for (parent, child) in unknown.into_iter().rev() {
+            known += weight(parent, child);
+            depths.insert(child, known);
+        }
+        known
+    };
+
+    let orbits = parents.keys().map(|&node| depth(node)).sum();
+
+    let you_parent = parents[&you];
+    let san_parent = parents[&san];
+
+    let mut ancestors = HashSet::new();
+    let mut current = Some(you_parent);
+    while let Some(node) = current {
+        ancestors.insert(node);
+        current = parents.get(&node).copied();
+    }
+
+    let mut lca = san_parent;
+    while !ancestors.contains(&lca) {
+        lca = parents[&lca];
+    }
+
+    let transfers = depth(you_parent) + depth(san_parent) - depth(lca) * 2;
+
+    (orbits, transfers)
+}
+
+fn main() -> Result<(), OrbitError> {
+    let mut path = None;
+    let mut weights = None;
+    let mut dot = false;
+    let mut low_memory = false;
+
+    let mut args = output::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--weights" => {
+                let file = input::open(args.next())?;
+                weights = Some(read_weights(BufReader::new(file)));
+            }
+            "--dot" => dot = true,
+            "--low-memory" => low_memory = true,
+            _ => path = Some(arg),
+        }
+    }
+
+    // Only keeps what each object orbits, never the child lists.
+    if low_memory {
+        let parents = read_parents(input::reader(path)?)?;
+
+        let (orbits, transfers) = solve_parents(&parents, weights.as_ref());
+        answer(1, orbits);
+        answer(2, transfers);
+        return Ok(());
+    }
+
+    let tree = read_input(input::reader(path)?);
+    let bodies = validate(&tree)?;
+    if dot {
+        print!("{}", to_dot(&tree));
+        return Ok(());
+    }
+
+    let now = Instant::now();
+
+    let (orbits, transfers) = solve(&tree, weights.as_ref());
 
     let elapsed = now.elapsed();
-    println!("{} {} {:?}", orbits, transfers, elapsed);
+    answer(1, orbits);
+    answer(2, transfers);
+    sayln!("bodies: {}", bodies);
+    sayln!("{:?}", elapsed);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "COM)B
+B)C
+C)D
+D)E
+E)F
+B)G
+G)H
+D)I
+E)J
+J)K
+K)L
+K)YOU
+I)SAN
+";
+
+    #[test]
+    fn read_sample_from_file() {
+        let path = std::env::temp_dir().join(format!("aoc2019-06-{}", std::process::id()));
+        std::fs::write(&path, SAMPLE).unwrap();
+        let file = input::open(Some(path.to_string_lossy().into_owned()));
+        let tree = read_input(BufReader::new(file.unwrap()));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(solve(&tree, None), (54, 4));
+    }
+
+    #[test]
+    fn body_with_two_parents() {
+        let orbits = "COM)A\nCOM)B\nA)C\nB)C\n";
+        let tree = read_input(orbits.as_bytes());
+        // Which parent the tree gives first depends on the map's order.
+        assert!(matches!(
+            validate(&tree),
+            Err(OrbitError::TwoParents(body, _, _)) if body == "C"
+        ));
+        assert!(matches!(
+            read_parents(orbits.as_bytes()),
+            Err(OrbitError::TwoParents(body, first, second))
+                if (body.as_str(), first.as_str(), second.as_str()) == ("C", "A", "B")
+        ));
+
+        assert_eq!(validate(&read_input(SAMPLE.as_bytes())).unwrap(), 14);
+    }
+
+    #[test]
+    fn parents_only() {
+        let parents = read_parents(SAMPLE.as_bytes()).unwrap();
+        assert_eq!(parents.len(), SAMPLE.lines().count());
+        assert_eq!(solve_parents(&parents, None), (54, 4));
+        assert_eq!(solve(&read_input(SAMPLE.as_bytes()), None), (54, 4));
+    }
+
+    #[test]
+    fn weighted_orbits() {
+        let tree = read_input(SAMPLE.as_bytes());
+        let weights = read_weights("COM)B 3\nD)I 10\n".as_bytes());
+        assert_eq!(solve(&tree, Some(&weights)), (98, 13));
+
+        let parents = read_parents(SAMPLE.as_bytes()).unwrap();
+        assert_eq!(solve_parents(&parents, Some(&weights)), (98, 13));
+    }
+
+    #[test]
+    fn dot_edges() {
+        let dot = to_dot(&read_input(SAMPLE.as_bytes()));
+        assert!(dot.starts_with("digraph orbits {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("    COM -> B;\n"));
+        assert!(dot.contains("    K -> YOU;\n"));
+        assert_eq!(dot.matches(" -> ").count(), SAMPLE.lines().count());
+    }
 }
@@ -1,22 +1,26 @@
+use aoc2019::hash::fnv1a;
+use aoc2019::input::{self, InputError};
+use aoc2019::ocr::ocr;
+use aoc2019::output::{self, answer};
+use aoc2019::{say, sayln};
 use std::cmp::Reverse;
 use std::collections::BinaryHeap;
-use std::io::{BufRead, Read};
+use std::io::Read;
 
 const WIDTH: usize = 25;
 const HEIGHT: usize = 6;
 
-fn read_input() -> Vec<u32> {
-    let mut stdin = std::io::stdin();
+fn read_input(path: Option<String>) -> Result<Vec<u32>, InputError> {
     let mut input = String::new();
-    stdin.read_to_string(&mut input).unwrap();
-    input
+    input::reader(path)?.read_to_string(&mut input).unwrap();
+    Ok(input
         .trim()
         .chars()
         .map(|c| c.to_digit(10).unwrap())
-        .collect()
+        .collect())
 }
 
-fn part1(input: &Vec<u32>) -> usize {
+fn part1(input: &[u32]) -> usize {
     let mut heap = BinaryHeap::new();
     for i in (0..input.len()).step_by(WIDTH * HEIGHT) {
         let layer = &input[i..i + WIDTH * HEIGHT];
@@ -29,28 +33,104 @@ fn part1(input: &Vec<u32>) -> usize {
     heap.pop().unwrap().1
 }
 
-fn part2(input: &Vec<u32>) {
-    let mut image = [2; WIDTH * HEIGHT];
-    for i in (0..input.len()).step_by(WIDTH * HEIGHT) {
-        let layer = &input[i..i + WIDTH * HEIGHT];
+// Stacks the layers, each pixel taking the colour of the first layer that
+// isn't transparent there.
+fn composite(input: &[u32], width: usize, height: usize) -> Vec<u32> {
+    let mut image = vec![2; width * height];
+    for layer in input.chunks(width * height) {
         for (i, pixel) in layer.iter().enumerate() {
             if image[i] == 2 {
                 image[i] = *pixel;
             }
         }
     }
+    image
+}
+
+// 64-bit FNV-1a
+fn checksum(pixels: &[u32]) -> u64 {
+    fnv1a(pixels.iter().map(|pixel| *pixel as u8))
+}
+
+// Returns the letters in the final image, its checksum, and how many of its
+// pixels are transparent in every layer.
+fn part2(input: &[u32]) -> (String, u64, usize) {
+    let image = composite(input, WIDTH, HEIGHT);
 
     for y in (0..image.len()).step_by(WIDTH) {
         for x in 0..WIDTH {
-            let pixel = if image[y + x] > 0 { "🦀" } else { "  " };
-            print!("{}", pixel);
+            let pixel = match image[y + x] {
+                0 => "  ",
+                1 => "🦀",
+                _ => "░░",
+            };
+            say!("{}", pixel);
         }
-        println!();
+        sayln!();
+    }
+
+    let grid: Vec<Vec<bool>> = image
+        .chunks(WIDTH)
+        .map(|row| row.iter().map(|pixel| *pixel == 1).collect())
+        .collect();
+
+    let transparent = image.iter().filter(|pixel| **pixel == 2).count();
+    (ocr(&grid), checksum(&image), transparent)
+}
+
+fn main() -> Result<(), InputError> {
+    let input = read_input(output::args().nth(1))?;
+    answer(1, part1(&input));
+    let (part2, checksum, transparent) = part2(&input);
+    answer(2, part2);
+    sayln!("checksum: {}", checksum);
+    if transparent > 0 {
+        sayln!("{} pixels are still transparent", transparent);
     }
+
+    Ok(())
 }
 
-fn main() {
-    let input = read_input();
-    dbg!(part1(&input));
-    part2(&input);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digits(text: &str) -> Vec<u32> {
+        text.chars().map(|c| c.to_digit(10).unwrap()).collect()
+    }
+
+    #[test]
+    fn checksum_of_example() {
+        let image = composite(&digits("123456789012"), 3, 2);
+        assert_eq!(image, vec![1, 8, 3, 4, 5, 6]);
+        assert_eq!(checksum(&image), 0x532b_cb8c_403a_3fd4);
+    }
+
+    #[test]
+    fn transparent_pixel() {
+        let mut bottom = vec![0; WIDTH * HEIGHT];
+        bottom[7] = 2;
+        let input = [vec![2; WIDTH * HEIGHT], bottom].concat();
+        let (_, _, transparent) = part2(&input);
+        assert_eq!(transparent, 1);
+    }
+
+    #[test]
+    fn letters_in_image() {
+        let rows = [
+            "#..#..###.####...........",
+            "#..#...#..#..............",
+            "####...#..###............",
+            "#..#...#..#..............",
+            "#..#...#..#..............",
+            "#..#..###.#..............",
+        ];
+        let input: Vec<u32> = rows
+            .concat()
+            .chars()
+            .map(|pixel| if pixel == '#' { 1 } else { 0 })
+            .collect();
+        let (letters, _, _) = part2(&input);
+        assert_eq!(letters, "HIF");
+    }
 }
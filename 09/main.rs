@@ -1,286 +1,340 @@
-use std::fmt::{Display, Formatter};
-use std::fs::File;
-use std::io::{BufRead, BufReader, Write};
-use std::sync::mpsc::{Receiver, Sender};
+use aoc2019::input::{self, InputError};
+use aoc2019::intcode::interpreter::{IntcodeError, Interpreter, Opcode};
+use aoc2019::intcode::memory::Memory;
+use aoc2019::intcode::Word;
+use aoc2019::output::{self, answer};
+use aoc2019::sayln;
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
-use text_io::{try_read, try_scan};
-
-#[derive(Debug)]
-enum Opcode {
-    Add,
-    Multiply,
-    Read,
-    Write,
-    JumpIfTrue,
-    JumpIfFalse,
-    LessThan,
-    Equals,
-    RelativeBase,
-    Halt,
+
+// Returns the outputs and the number of instructions it took. Without `inputs`
+// the program reads from the terminal. `debug` prints every instruction.
+fn execute(memory: &[Word], inputs: Option<&[Word]>, debug: bool) -> (Vec<Word>, u64) {
+    execute_on(Interpreter::new(memory), inputs, debug)
 }
 
-impl From<i128> for Opcode {
-    fn from(item: i128) -> Self {
-        match item {
-            1 => Opcode::Add,
-            2 => Opcode::Multiply,
-            3 => Opcode::Read,
-            4 => Opcode::Write,
-            5 => Opcode::JumpIfTrue,
-            6 => Opcode::JumpIfFalse,
-            7 => Opcode::LessThan,
-            8 => Opcode::Equals,
-            9 => Opcode::RelativeBase,
-            99 => Opcode::Halt,
-            _ => panic!("unknown instruction {}", item),
-        }
+fn execute_on<M: Memory>(
+    mut interpreter: Interpreter<M>,
+    inputs: Option<&[Word]>,
+    debug: bool,
+) -> (Vec<Word>, u64) {
+    interpreter.debug = debug;
+    if let Some(inputs) = inputs {
+        interpreter.inputs.extend(inputs);
     }
-}
 
-#[derive(Debug, PartialEq, Eq)]
-enum ParameterMode {
-    Position,
-    Immediate,
-    Relative,
+    interpreter.run().unwrap();
+    (interpreter.outputs.into(), interpreter.cycles)
 }
 
-impl From<i128> for ParameterMode {
-    fn from(item: i128) -> Self {
-        match item {
-            0 => ParameterMode::Position,
-            1 => ParameterMode::Immediate,
-            2 => ParameterMode::Relative,
-            _ => panic!("unknown parameter mode {}", item),
-        }
-    }
+// Runs the program reading from the terminal, and returns its outputs along
+// with everything that was typed, so the session can be replayed with execute.
+fn record_session(memory: &[Word]) -> (Vec<Word>, Vec<Word>) {
+    let mut interpreter = Interpreter::new(memory);
+    interpreter.recorded = Some(Vec::new());
+    interpreter.run().unwrap();
+    (interpreter.outputs.into(), interpreter.recorded.unwrap())
 }
 
-#[derive(Debug)]
-struct Parameter {
-    pub mode: ParameterMode,
-    pub value: i128,
+// Whether both programs give the same outputs for every set of inputs.
+fn equivalent(a: &[Word], b: &[Word], inputs: &[Vec<Word>]) -> bool {
+    inputs
+        .iter()
+        .all(|inputs| execute(a, Some(inputs), false).0 == execute(b, Some(inputs), false).0)
 }
 
-impl Parameter {
-    fn new(mode: ParameterMode, value: i128) -> Self {
-        Self { mode, value }
-    }
-}
+// Runs the program and records a line per instruction: its ip, its opcode and
+// every write it made, as `address=value`.
+fn trace(memory: &[Word], inputs: &[Word]) -> Vec<String> {
+    let mut interpreter = Interpreter::new(memory);
+    interpreter.inputs.extend(inputs);
+    interpreter.echo_output = false;
+
+    let writes = Arc::new(Mutex::new(Vec::new()));
+    let writes_ = writes.clone();
+    interpreter.on_write(Box::new(move |address, value| {
+        writes_
+            .lock()
+            .unwrap()
+            .push(format!("{}={}", address, value))
+    }));
+
+    let mut lines = Vec::new();
+    loop {
+        let ip = interpreter.ip;
+        let opcode = interpreter.instruction().unwrap().opcode;
+        let running = interpreter.step().unwrap();
+
+        let mut line = format!("{} {:?}", ip, opcode);
+        for write in writes.lock().unwrap().drain(..) {
+            line.push(' ');
+            line.push_str(&write);
+        }
+        lines.push(line);
 
-impl Display for Parameter {
-    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
-        match self.mode {
-            ParameterMode::Immediate => write!(formatter, "{}", self.value),
-            ParameterMode::Position => write!(formatter, "[{}]", self.value),
-            ParameterMode::Relative => write!(formatter, "rel[{}]", self.value),
+        if !running {
+            return lines;
         }
     }
 }
 
-#[derive(Debug)]
-struct Instruction {
-    pub opcode: Opcode,
-    pub parameters: (Parameter, Parameter, Parameter),
+// Returns the first line where the traces differ, if they do.
+fn diff_trace(expected: &[String], actual: &[String]) -> Option<usize> {
+    let common = expected.len().min(actual.len());
+    (0..common)
+        .find(|&i| expected[i] != actual[i])
+        .or(if expected.len() != actual.len() {
+            Some(common)
+        } else {
+            None
+        })
 }
 
-impl Instruction {
-    pub fn fetch(ip: i128, memory: &Vec<i128>) -> Option<Self> {
-        let ip = ip as usize;
-        let instruction = memory.get(ip)?;
-
-        let opcode = Opcode::from(instruction % 100);
-        let parameters = (
-            Parameter::new(
-                ParameterMode::from(instruction / 100 % 10),
-                *memory.get(ip + 1).unwrap_or(&0),
-            ),
-            Parameter::new(
-                ParameterMode::from(instruction / 1000 % 10),
-                *memory.get(ip + 2).unwrap_or(&0),
-            ),
-            Parameter::new(
-                ParameterMode::from(instruction / 10000 % 10),
-                *memory.get(ip + 3).unwrap_or(&0),
-            ),
-        );
+// Runs an ASCII program, writing its output as text to `writer` and asking
+// `get_line` for a line whenever it wants input that hasn't been given yet.
+// Lines are fed in byte by byte, followed by a newline.
+fn run_interactive(
+    memory: &[Word],
+    writer: &mut impl Write,
+    mut get_line: impl FnMut() -> String,
+) -> Result<(), IntcodeError> {
+    let mut interpreter = Interpreter::new(memory);
+    interpreter.echo_output = false;
+
+    loop {
+        let instruction = interpreter.instruction()?;
+        if instruction.opcode == Opcode::Read && interpreter.inputs.is_empty() {
+            writer.flush().unwrap();
+            for byte in get_line().bytes().chain(std::iter::once(b'\n')) {
+                interpreter.push_input(Word::from(byte));
+            }
+        }
+
+        let running = interpreter.step()?;
+        while let Some(value) = interpreter.pop_output() {
+            // Anything that isn't ASCII is most likely an answer.
+            match u8::try_from(value) {
+                Ok(byte) if byte.is_ascii() => write!(writer, "{}", byte as char),
+                _ => writeln!(writer, "{}", value),
+            }
+            .unwrap();
+        }
 
-        Some(Self { opcode, parameters })
+        if !running {
+            return Ok(());
+        }
     }
 }
 
-struct Interpreter {
-    pub memory: Vec<i128>,
-    pub rx: Option<Receiver<i128>>,
-    pub tx: Option<Sender<i128>>,
-    pub last_output: Option<i128>,
-    pub ip: i128,
-    pub relative_base: i128,
-    pub debug: bool,
+// Runs BOOST with `mode` as its only input: 1 is test mode, 2 is sensor boost.
+fn boost(memory: &[Word], mode: Word, debug: bool, sparse: bool) -> (Word, u64) {
+    let inputs = Some(&[mode][..]);
+    let (outputs, cycles) = if sparse {
+        execute_on(Interpreter::sparse(memory), inputs, debug)
+    } else {
+        execute(memory, inputs, debug)
+    };
+    if outputs.len() > 1 {
+        sayln!("diagnostics: {:?}", &outputs[..outputs.len() - 1]);
+    }
+    (*outputs.last().unwrap(), cycles)
 }
 
-impl Interpreter {
-    fn new(memory: &Vec<i128>) -> Self {
-        Self {
-            memory: memory.clone(),
-            rx: None,
-            tx: None,
-            last_output: None,
-            ip: 0,
-            relative_base: 0,
-            debug: true,
+fn main() -> Result<(), InputError> {
+    let mut filename = None;
+    let mut other = None;
+    let mut record_trace = None;
+    let mut check_trace = None;
+    let mut interactive = false;
+    let mut debug = false;
+    let mut sparse = false;
+    let mut record_inputs = None;
+    let mut replay_inputs = None;
+
+    let mut args = output::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--equivalent" => other = Some(args.next()),
+            "--record-trace" => record_trace = args.next(),
+            "--check-trace" => check_trace = args.next(),
+            "--interactive" => interactive = true,
+            "--debug" => debug = true,
+            "--sparse" => sparse = true,
+            "--record-inputs" => record_inputs = args.next(),
+            "--replay-inputs" => replay_inputs = args.next(),
+            _ => filename = Some(arg),
         }
     }
 
-    pub fn step(&mut self) -> bool {
-        let instruction = Instruction::fetch(self.ip, &&self.memory).unwrap();
-        let (a, b, c) = &instruction.parameters;
+    let memory = input::read_program(filename)?;
 
-        // if self.debug {
-        //     print!("{:?} {}, {}, {}", instruction.opcode, a, b, c);
-        //     std::io::stdout().flush().unwrap();
-        // }
-
-        let (ip, arg_count) = match instruction.opcode {
-            Opcode::Add => {
-                *self.value_mut(&c) = self.value(&a) + self.value(&b);
-                (self.ip + 4, 3)
-            }
-
-            Opcode::Multiply => {
-                *self.value_mut(&c) = self.value(&a) * self.value(&b);
-                (self.ip + 4, 3)
-            }
-
-            Opcode::Read => {
-                *self.value_mut(&c) = match &self.rx {
-                    Some(rx) => rx.recv().unwrap(),
-                    None => try_read!().unwrap(),
-                };
-                (self.ip + 2, 1)
+    // Talks to an ASCII program over stdin and stdout, stopping at end of input.
+    if interactive {
+        run_interactive(&memory, &mut std::io::stdout(), || {
+            let mut line = String::new();
+            if std::io::stdin().read_line(&mut line).unwrap() == 0 {
+                std::process::exit(0);
             }
+            line.trim_end_matches('\n').to_string()
+        })
+        .unwrap();
+        return Ok(());
+    }
 
-            Opcode::Write => {
-                let value = self.value(&a);
-                self.last_output = Some(value);
-                match &self.tx {
-                    Some(tx) => {
-                        let _ = tx.send(value);
-                    }
-                    None => println!("> {}", value),
-                }
-                (self.ip + 2, 1)
-            }
+    // Saves what was typed one input per line, and plays it back later.
+    if let Some(path) = record_inputs {
+        let (outputs, inputs) = record_session(&memory);
+        let text: String = inputs.iter().map(|input| format!("{}\n", input)).collect();
+        std::fs::write(path, text).unwrap();
+        println!("outputs: {:?}", outputs);
+        return Ok(());
+    }
 
-            Opcode::JumpIfTrue => (
-                if self.value(&a) != 0 {
-                    self.value(&b)
-                } else {
-                    self.ip + 3
-                },
-                2,
-            ),
-
-            Opcode::JumpIfFalse => (
-                if self.value(&a) == 0 {
-                    self.value(&b)
-                } else {
-                    self.ip + 3
-                },
-                2,
-            ),
-
-            Opcode::LessThan => {
-                let result = self.value(&a) < self.value(&b);
-                *self.value_mut(&c) = if result { 1 } else { 0 };
-                (self.ip + 4, 3)
-            }
+    if let Some(path) = replay_inputs {
+        let mut text = String::new();
+        input::open(Some(path))?.read_to_string(&mut text).unwrap();
+        let inputs: Vec<Word> = text.lines().map(|line| line.parse().unwrap()).collect();
+        let (outputs, _) = execute(&memory, Some(&inputs), debug);
+        println!("outputs: {:?}", outputs);
+        return Ok(());
+    }
 
-            Opcode::Equals => {
-                let result = self.value(&a) == self.value(&b);
-                *self.value_mut(&c) = if result { 1 } else { 0 };
-                (self.ip + 4, 3)
-            }
+    // Compares against another program in both of BOOST's modes.
+    if let Some(other) = other {
+        let other = input::read_program(other)?;
+        println!(
+            "equivalent: {}",
+            equivalent(&memory, &other, &[vec![1], vec![2]])
+        );
+        return Ok(());
+    }
 
-            Opcode::RelativeBase => {
-                self.relative_base += self.value(&a);
-                (self.ip + 2, 1)
-            }
+    // Traces run in BOOST's test mode.
+    if let Some(path) = record_trace {
+        let mut text = trace(&memory, &[1]).join("\n");
+        text.push('\n');
+        std::fs::write(path, text).unwrap();
+        return Ok(());
+    }
 
-            Opcode::Halt => {
-                return false;
+    if let Some(path) = check_trace {
+        let mut text = String::new();
+        input::open(Some(path))?.read_to_string(&mut text).unwrap();
+        let expected: Vec<String> = text.lines().map(String::from).collect();
+        let actual = trace(&memory, &[1]);
+        match diff_trace(&expected, &actual) {
+            None => println!("trace matches"),
+            Some(i) => {
+                let line = |trace: &[String]| trace.get(i).cloned().unwrap_or_default();
+                println!("trace differs at instruction {}:", i);
+                println!("  expected: {}", line(&expected));
+                println!("  actual:   {}", line(&actual));
+                std::process::exit(1);
             }
-        };
-        self.ip = ip;
-
-        if self.debug {
-            let args = match arg_count {
-                1 => format!("{:?} {}", instruction.opcode, a),
-                2 => format!("{:?} {}, {}", instruction.opcode, a, b),
-                3 => format!("{:?} {}, {}, {}", instruction.opcode, a, b, c),
-                _ => panic!(),
-            };
-
-            println!(
-                "\rip={:<5} rb={:<5} | {:<30} | {:?}",
-                ip,
-                self.relative_base,
-                args,
-                self.memory.iter().max()
-            );
         }
-
-        true
+        return Ok(());
     }
 
-    pub fn run(&mut self) {
-        while self.step() {}
+    for mode in 1..=2 {
+        let now = Instant::now();
+        let (result, cycles) = boost(&memory, mode, debug, sparse);
+        let elapsed = now.elapsed();
+
+        answer(mode as u8, result);
+        sayln!(
+            "{} instructions in {:?} ({:.0} ips)",
+            cycles,
+            elapsed,
+            cycles as f64 / elapsed.as_secs_f64()
+        );
     }
 
-    fn value(&self, parameter: &Parameter) -> i128 {
-        let index = match parameter.mode {
-            ParameterMode::Position => parameter.value as usize,
-            ParameterMode::Relative => (parameter.value + self.relative_base) as usize,
-            ParameterMode::Immediate => {
-                return parameter.value;
-            }
-        };
-
-        *self.memory.get(index).unwrap_or(&0)
-    }
+    Ok(())
+}
 
-    fn value_mut<'a>(&'a mut self, parameter: &Parameter) -> &'a mut i128 {
-        let index = match parameter.mode {
-            ParameterMode::Position => parameter.value as usize,
-            ParameterMode::Relative => (parameter.value + self.relative_base) as usize,
-            ParameterMode::Immediate => panic!("can't get immediate as mut"),
-        };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aoc2019::intcode::IntcodeProgram;
+
+    #[test]
+    fn negative_relative_address() {
+        let mut interpreter = Interpreter::new(&[109, -1, 204, 0, 99]);
+        assert_eq!(
+            interpreter.run(),
+            Err(IntcodeError::NegativeAddress { address: -1, ip: 2 })
+        );
 
-        if index >= self.memory.len() {
-            println!("resizing memory to {}", index);
-            self.memory.resize(index + 1, 0);
-        }
+        // A negative base is fine as long as the address it makes isn't.
+        let (outputs, _) = execute(&[109, -1, 204, 3, 99], Some(&[]), false);
+        assert_eq!(outputs, vec![204]);
+    }
 
-        self.memory.get_mut(index).unwrap()
+    #[test]
+    fn all_outputs() {
+        let memory = vec![104, 1, 104, 2, 104, 3, 99];
+        assert_eq!(execute(&memory, Some(&[]), false).0, vec![1, 2, 3]);
+        assert_eq!(boost(&memory, 1, false, false).0, 3);
     }
-}
 
-fn part1(memory: &Vec<i128>) -> i128 {
-    let mut interpreter = Interpreter::new(memory);
-    interpreter.run();
-    interpreter.last_output.unwrap()
-}
+    #[test]
+    fn quine_cycles() {
+        let quine = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        // The whole loop of five instructions for every word copied, then the
+        // halt.
+        let (outputs, cycles) = execute(&quine, Some(&[]), false);
+        assert_eq!(outputs, quine);
+        assert_eq!(cycles, 16 * 5 + 1);
+    }
 
-fn main() {
-    let file = File::open(std::env::args().nth(1).expect("no filename provided")).unwrap();
-    let mut input = String::new();
-    BufReader::new(file).read_line(&mut input).unwrap();
+    #[test]
+    fn equivalent_programs() {
+        // Both output whether the input equals 8, one in position mode and one
+        // in immediate mode. The last outputs whether it's less than 8.
+        let position = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+        let immediate = vec![3, 3, 1108, -1, 8, 3, 4, 3, 99];
+        let less_than = vec![3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8];
+
+        let inputs = vec![vec![7], vec![8], vec![9]];
+        assert!(equivalent(&position, &immediate, &inputs));
+        assert!(!equivalent(&position, &less_than, &inputs));
+        assert!(equivalent(&position, &less_than, &[vec![9]]));
+    }
 
-    let memory: Vec<i128> = input
-        .split(",")
-        .map(|x| x.trim().parse().unwrap())
-        .collect();
+    #[test]
+    fn quine_matches_golden_trace() {
+        let quine: Vec<Word> = include_str!("example1.in")
+            .parse::<IntcodeProgram>()
+            .unwrap()
+            .into();
+        let expected: Vec<String> = include_str!("example1.trace")
+            .lines()
+            .map(String::from)
+            .collect();
+        let actual = trace(&quine, &[1]);
+        assert_eq!(diff_trace(&expected, &actual), None);
+
+        assert_eq!(diff_trace(&expected, &actual[..10]), Some(10));
+        let mut changed = actual.clone();
+        changed[3].push_str(" 0=0");
+        assert_eq!(diff_trace(&expected, &changed), Some(3));
+    }
 
-    let now = Instant::now();
-    dbg!(part1(&memory));
-    dbg!(now.elapsed());
+    #[test]
+    fn interactive_uppercase() {
+        // Prompts with "> ", reads a line and writes it back uppercased.
+        let memory = vec![
+            104, 62, 104, 32, 3, 100, 1008, 100, 10, 101, 1005, 101, 36, 1007, 100, 97, 101, 1005,
+            101, 31, 1007, 100, 123, 101, 1006, 101, 31, 1001, 100, -32, 100, 4, 100, 1105, 1, 4,
+            104, 10, 99,
+        ];
+        let mut output = Vec::new();
+        let mut lines = vec!["Hi there!".to_string()];
+        run_interactive(&memory, &mut output, || lines.pop().unwrap()).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "> HI THERE!\n");
+    }
 }
@@ -1,26 +1,136 @@
-use std::collections::{BTreeMap, BinaryHeap, VecDeque};
-use std::f64;
+use aoc2019::input::{self, InputError};
+use aoc2019::math::reduce;
+use aoc2019::output::{self, answer};
+use aoc2019::sayln;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Display, Formatter};
 use std::io::BufRead;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 struct Point {
     x: usize,
     y: usize,
 }
 
-fn read_input() -> (Vec<Point>, (usize, usize)) {
-    let stdin = std::io::stdin();
+impl From<(usize, usize)> for Point {
+    fn from((x, y): (usize, usize)) -> Self {
+        Self { x, y }
+    }
+}
+
+// A direction from the station, reduced so that all asteroids on the same line
+// of sight share the same angle. `dy` points up. Ordered clockwise, starting
+// straight up.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct Angle {
+    dx: i64,
+    dy: i64,
+}
+
+impl Angle {
+    fn new(dx: i64, dy: i64) -> Self {
+        let (dx, dy) = reduce(dx, dy);
+        Self { dx, dy }
+    }
+
+    // 0 for the right half (including straight up), 1 for the left half
+    // (including straight down).
+    fn half(&self) -> u8 {
+        if self.dx > 0 || (self.dx == 0 && self.dy > 0) {
+            0
+        } else {
+            1
+        }
+    }
+}
+
+impl Ord for Angle {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.half()
+            .cmp(&other.half())
+            .then_with(|| (self.dx * other.dy - self.dy * other.dx).cmp(&0))
+    }
+}
+
+impl PartialOrd for Angle {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+enum FieldError {
+    Input(InputError),
+    RaggedRow {
+        y: usize,
+        expected: usize,
+        found: usize,
+    },
+    OutOfBounds(Point),
+}
+
+impl From<InputError> for FieldError {
+    fn from(error: InputError) -> Self {
+        FieldError::Input(error)
+    }
+}
+
+impl Display for FieldError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            FieldError::Input(error) => write!(formatter, "{}", error),
+            FieldError::RaggedRow { y, expected, found } => write!(
+                formatter,
+                "row {} is {} wide, expected {}",
+                y, found, expected
+            ),
+            FieldError::OutOfBounds(asteroid) => write!(
+                formatter,
+                "asteroid {},{} is outside the field",
+                asteroid.x, asteroid.y
+            ),
+        }
+    }
+}
+
+impl Debug for FieldError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        Display::fmt(self, formatter)
+    }
+}
+
+// Reads either a `#`/`.` grid or a list of `x,y` coordinates, one per line.
+fn read_input(path: Option<String>) -> Result<(Vec<Point>, (usize, usize)), FieldError> {
+    let lines: Vec<String> = input::reader(path)?
+        .lines()
+        .map(|line| line.unwrap())
+        .collect();
+
+    if lines.first().is_some_and(|line| line.contains(',')) {
+        let coordinates = parse_coordinates(&lines);
+        Ok(from_coordinates(&coordinates))
+    } else {
+        parse_grid(&lines)
+    }
+}
+
+fn parse_grid(lines: &[String]) -> Result<(Vec<Point>, (usize, usize)), FieldError> {
     let mut asteroids = Vec::new();
     let mut width = 0;
     let mut height = 0;
 
-    for (y, line) in stdin.lock().lines().enumerate() {
-        let line = line.unwrap();
+    for (y, line) in lines.iter().enumerate() {
         let line = line.trim();
 
         height += 1;
         if width == 0 {
             width = line.len();
+        } else if line.len() != width {
+            return Err(FieldError::RaggedRow {
+                y,
+                expected: width,
+                found: line.len(),
+            });
         }
 
         for (x, c) in line.trim().chars().enumerate() {
@@ -30,37 +140,75 @@ fn read_input() -> (Vec<Point>, (usize, usize)) {
         }
     }
 
+    Ok((asteroids, (width, height)))
+}
+
+fn parse_coordinates(lines: &[String]) -> Vec<(usize, usize)> {
+    lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut coords = line.trim().split(',').map(|c| c.trim().parse().unwrap());
+            (coords.next().unwrap(), coords.next().unwrap())
+        })
+        .collect()
+}
+
+// A coordinate list has no grid around it, so the field is just big enough to
+// hold every asteroid.
+fn from_coordinates(coordinates: &[(usize, usize)]) -> (Vec<Point>, (usize, usize)) {
+    let asteroids: Vec<Point> = coordinates.iter().map(|&c| Point::from(c)).collect();
+    let width = asteroids.iter().map(|a| a.x + 1).max().unwrap_or(0);
+    let height = asteroids.iter().map(|a| a.y + 1).max().unwrap_or(0);
     (asteroids, (width, height))
 }
 
-fn get_sight<'a>(station: &Point, asteroids: &'a Vec<Point>) -> BTreeMap<i64, Vec<&'a Point>> {
+fn validate(asteroids: &[Point], (width, height): (usize, usize)) -> Result<(), FieldError> {
+    match asteroids.iter().find(|a| a.x >= width || a.y >= height) {
+        Some(asteroid) => Err(FieldError::OutOfBounds(*asteroid)),
+        None => Ok(()),
+    }
+}
+
+type Sight<'a> = BTreeMap<Angle, Vec<&'a Point>>;
+
+fn get_sight<'a>(station: &Point, asteroids: &'a [Point]) -> Sight<'a> {
     let mut angles = BTreeMap::new();
     for asteroid in asteroids {
         if asteroid.x == station.x && asteroid.y == station.y {
             continue;
         }
 
-        let dy = station.y as f64 - asteroid.y as f64;
-        let dx = asteroid.x as f64 - station.x as f64;
-        let angle = (dx.atan2(dy) * 180.0 * 256.0 / f64::consts::PI).round() as i64;
-        let angle = (angle + 360 * 256) % (360 * 256);
+        let dy = station.y as i64 - asteroid.y as i64;
+        let dx = asteroid.x as i64 - station.x as i64;
+        let angle = Angle::new(dx, dy);
         angles.entry(angle).or_insert_with(Vec::new).push(asteroid);
     }
 
     angles
 }
 
-fn part1(input: &Vec<Point>) -> (usize, &Point) {
-    let mut sights = BinaryHeap::new();
+// Returns the best station along with how many asteroids it sees and its sight,
+// so part 2 doesn't have to compute it again.
+fn part1(input: &[Point]) -> (usize, &Point, Sight<'_>) {
+    let mut best: Option<(usize, &Point, Sight)> = None;
     for station in input {
-        let sight = get_sight(&station, input);
-        sights.push((sight.len(), station));
+        let sight = get_sight(station, input);
+        let better = match &best {
+            Some((count, best_station, _)) => (sight.len(), station) > (*count, *best_station),
+            None => true,
+        };
+        if better {
+            best = Some((sight.len(), station, sight));
+        }
     }
-    sights.pop().unwrap()
+    best.unwrap()
 }
 
-fn part2(input: &Vec<Point>, station: &Point) -> usize {
-    let mut sight = get_sight(&station, input);
+// Returns the `n`th asteroid to be vaporized, counting from 1, along with how
+// many full rotations the laser completed before hitting it. None if there
+// aren't that many asteroids.
+fn nth_vaporized(station: &Point, mut sight: Sight, n: usize) -> Option<(Point, usize)> {
     for (_, asteroids) in sight.iter_mut() {
         asteroids.sort_by_key(|a| {
             -(station.x as i64 - a.x as i64).pow(2) - (station.y as i64 - a.y as i64).pow(2)
@@ -68,10 +216,10 @@ fn part2(input: &Vec<Point>, station: &Point) -> usize {
     }
 
     let mut obliterated = 0;
+    let mut rotations = 0;
     loop {
         let mut count = 0;
         for (angle, asteroids) in sight.iter_mut() {
-            let angle = *angle as f64 / 256.0;
             let asteroid = match asteroids.pop() {
                 Some(v) => v,
                 None => continue,
@@ -80,24 +228,177 @@ fn part2(input: &Vec<Point>, station: &Point) -> usize {
             count += 1;
 
             obliterated += 1;
-            println!("{:>5}: {:.1}, {:?}", obliterated, angle, asteroid);
-            if obliterated == 200 {
-                return asteroid.x * 100 + asteroid.y;
+            sayln!("{:>5}: {:?}, {:?}", obliterated, angle, asteroid);
+            if obliterated == n {
+                return Some((*asteroid, rotations));
             }
         }
 
         if count == 0 {
-            break;
+            return None;
         }
+        rotations += 1;
     }
+}
 
-    0
+// Gives 0 if there aren't 200 asteroids to vaporize.
+fn part2(station: &Point, sight: Sight) -> (usize, usize) {
+    nth_vaporized(station, sight, 200).map_or((0, 0), |(asteroid, rotations)| {
+        (asteroid.x * 100 + asteroid.y, rotations)
+    })
 }
 
-fn main() {
-    let (input, (_, _)) = read_input();
+fn main() -> Result<(), FieldError> {
+    // `--nth N` reports the Nth asteroid to be vaporized instead of the 200th.
+    let mut filename = None;
+    let mut nth = None;
+    let mut args = output::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--nth" => nth = Some(args.next().expect("--nth needs a count").parse().unwrap()),
+            _ => filename = Some(arg),
+        }
+    }
+
+    let (input, size) = read_input(filename)?;
+    validate(&input, size)?;
+
+    let (part1, station, sight) = part1(&input);
+    answer(1, part1);
+
+    if let Some(n) = nth {
+        match nth_vaporized(station, sight, n) {
+            Some((asteroid, _)) => println!("asteroid {}: {},{}", n, asteroid.x, asteroid.y),
+            None => println!("fewer than {} asteroids to vaporize", n),
+        }
+        return Ok(());
+    }
+
+    let (part2, rotations) = part2(station, sight);
+    answer(2, part2);
+    sayln!("rotations: {}", rotations);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(text: &str) -> Vec<Point> {
+        let lines: Vec<String> = text.lines().map(String::from).collect();
+        parse_grid(&lines).unwrap().0
+    }
+
+    // Clockwise from straight up, the way `atan2` keys used to order them.
+    fn float_angle(angle: &Angle) -> f64 {
+        let radians = (angle.dx as f64).atan2(angle.dy as f64);
+        if radians < 0.0 {
+            radians + 2.0 * std::f64::consts::PI
+        } else {
+            radians
+        }
+    }
+
+    #[test]
+    fn rational_angles_match_float_angles() {
+        let asteroids = field(include_str!("example5.in"));
+        let sight = get_sight(&Point { x: 11, y: 13 }, &asteroids);
+
+        let rational: Vec<Angle> = sight.keys().cloned().collect();
+        let mut float = rational.clone();
+        float.sort_by(|a, b| float_angle(a).partial_cmp(&float_angle(b)).unwrap());
+        assert_eq!(rational, float);
+    }
+
+    #[test]
+    fn part2_reuses_sight() {
+        let asteroids = field(include_str!("example5.in"));
+        let (count, station, sight) = part1(&asteroids);
+        assert_eq!((count, *station), (210, Point { x: 11, y: 13 }));
+
+        let recomputed = get_sight(station, &asteroids);
+        assert_eq!(part2(station, sight), part2(station, recomputed));
+        assert_eq!(part2(station, get_sight(station, &asteroids)).0, 802);
+    }
+
+    #[test]
+    fn rotations_before_vaporizing() {
+        let asteroids = field(include_str!("example5.in"));
+        let station = Point { x: 11, y: 13 };
+        let sight = || get_sight(&station, &asteroids);
+
+        // The first rotation hits all 210 asteroids the station can see.
+        assert_eq!(part2(&station, sight()), (802, 0));
+        assert_eq!(nth_vaporized(&station, sight(), 210).unwrap().1, 0);
+        assert_eq!(nth_vaporized(&station, sight(), 211).unwrap().1, 1);
+        assert_eq!(
+            nth_vaporized(&station, sight(), 299).map(|(asteroid, _)| asteroid),
+            Some(Point { x: 11, y: 1 })
+        );
+        assert_eq!(nth_vaporized(&station, sight(), 300), None);
+    }
 
-    let (part1, station) = part1(&input);
-    dbg!(part1);
-    dbg!(part2(&input, &station));
+    #[test]
+    fn first_vaporized() {
+        let asteroids = field(include_str!("example5.in"));
+        let station = Point { x: 11, y: 13 };
+        let nth = |n| {
+            nth_vaporized(&station, get_sight(&station, &asteroids), n)
+                .map(|(asteroid, _)| asteroid)
+        };
+        assert_eq!(nth(1), Some(Point { x: 11, y: 12 }));
+        assert_eq!(nth(2), Some(Point { x: 12, y: 1 }));
+        assert_eq!(nth(3), Some(Point { x: 12, y: 2 }));
+        assert_eq!(nth(1000), None);
+    }
+
+    #[test]
+    fn coordinate_list() {
+        // The first example, as coordinates.
+        let coordinates = [
+            (1, 0),
+            (4, 0),
+            (0, 2),
+            (1, 2),
+            (2, 2),
+            (3, 2),
+            (4, 2),
+            (4, 3),
+            (3, 4),
+            (4, 4),
+        ];
+        let (asteroids, size) = from_coordinates(&coordinates);
+        assert_eq!(size, (5, 5));
+        assert_eq!(asteroids, field(include_str!("example1.in")));
+
+        let (count, station, _) = part1(&asteroids);
+        assert_eq!((count, *station), (8, Point { x: 3, y: 4 }));
+
+        let lines: Vec<String> = vec!["1,0".into(), " 4, 0 ".into(), "".into()];
+        assert_eq!(parse_coordinates(&lines), vec![(1, 0), (4, 0)]);
+    }
+
+    #[test]
+    fn ragged_field() {
+        let lines: Vec<String> = vec![".#..#".into(), ".....".into(), "###".into()];
+        assert!(matches!(
+            parse_grid(&lines),
+            Err(FieldError::RaggedRow {
+                y: 2,
+                expected: 5,
+                found: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn asteroid_outside_field() {
+        let asteroids = vec![Point { x: 1, y: 1 }, Point { x: 5, y: 0 }];
+        assert!(matches!(
+            validate(&asteroids, (5, 5)),
+            Err(FieldError::OutOfBounds(Point { x: 5, y: 0 }))
+        ));
+        assert!(validate(&asteroids, (6, 2)).is_ok());
+    }
 }
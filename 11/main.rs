@@ -1,11 +1,12 @@
-use std::collections::HashSet;
-use std::convert::{From, TryFrom};
-use std::fmt::{Display, Formatter};
-use std::fs::File;
-use std::io::{BufRead, BufReader, Write};
-use std::sync::mpsc::{channel, Receiver, Sender};
+use aoc2019::input::{self, InputError};
+use aoc2019::intcode::interpreter::{IntcodeError, Interpreter};
+use aoc2019::intcode::machine::Machine;
+use aoc2019::ocr::ocr;
+use aoc2019::output::{self, answer};
+use aoc2019::{say, sayln};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Display, Formatter};
 use std::time::Instant;
-use text_io::{try_read, try_scan};
 
 #[derive(Debug)]
 enum Direction {
@@ -47,359 +48,348 @@ impl Direction {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum Opcode {
-    Add,
-    Multiply,
-    Read,
-    Write,
-    JumpIfTrue,
-    JumpIfFalse,
-    LessThan,
-    Equals,
-    RelativeBase,
-    Halt,
+enum RobotError {
+    Input(InputError),
+    // The program halted after a colour, without saying which way to turn.
+    HaltedMidPair(i64),
+    Intcode(IntcodeError),
 }
 
-impl From<i64> for Opcode {
-    fn from(item: i64) -> Self {
-        match item {
-            1 => Opcode::Add,
-            2 => Opcode::Multiply,
-            3 => Opcode::Read,
-            4 => Opcode::Write,
-            5 => Opcode::JumpIfTrue,
-            6 => Opcode::JumpIfFalse,
-            7 => Opcode::LessThan,
-            8 => Opcode::Equals,
-            9 => Opcode::RelativeBase,
-            99 => Opcode::Halt,
-            _ => panic!("unknown instruction {}", item),
-        }
+impl From<InputError> for RobotError {
+    fn from(error: InputError) -> Self {
+        RobotError::Input(error)
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum ParameterMode {
-    Position,
-    Immediate,
-    Relative,
+impl From<IntcodeError> for RobotError {
+    fn from(error: IntcodeError) -> Self {
+        RobotError::Intcode(error)
+    }
 }
 
-impl From<i64> for ParameterMode {
-    fn from(item: i64) -> Self {
-        match item {
-            0 => ParameterMode::Position,
-            1 => ParameterMode::Immediate,
-            2 => ParameterMode::Relative,
-            _ => panic!("unknown parameter mode {}", item),
+impl Display for RobotError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            RobotError::Input(error) => write!(formatter, "{}", error),
+            RobotError::HaltedMidPair(color) => write!(
+                formatter,
+                "robot halted after painting colour {} without turning",
+                color
+            ),
+            RobotError::Intcode(error) => write!(formatter, "{}", error),
         }
     }
 }
 
-#[derive(Debug)]
-struct Parameter {
-    pub mode: ParameterMode,
-    pub value: i64,
+impl Debug for RobotError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        Display::fmt(self, formatter)
+    }
 }
 
-impl Parameter {
-    fn new(mode: ParameterMode, value: i64) -> Self {
-        Self { mode, value }
-    }
+// Width and height of the hull; the robot starts in the middle.
+const SIZE: usize = 200;
+
+// The painted part of the hull, the way round it reads: the robot's x runs down
+// the rows, flipped, and its y runs along them.
+fn hull(map: &[Vec<bool>], painted: &HashSet<(usize, usize)>) -> Vec<Vec<bool>> {
+    let min_x = painted.iter().map(|(x, _)| *x).min().unwrap_or(0);
+    let max_x = painted.iter().map(|(x, _)| *x).max().unwrap_or(0);
+    let min_y = painted.iter().map(|(_, y)| *y).min().unwrap_or(0);
+    let max_y = painted.iter().map(|(_, y)| *y).max().unwrap_or(0);
+
+    (min_x..=max_x)
+        .rev()
+        .map(|x| (min_y..=max_y).map(|y| map[y][x]).collect())
+        .collect()
 }
 
-impl Display for Parameter {
-    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
-        match self.mode {
-            ParameterMode::Immediate => write!(formatter, "{}", self.value),
-            ParameterMode::Position => write!(formatter, "[{}]", self.value),
-            ParameterMode::Relative => write!(formatter, "rel[{}]", self.value),
+fn render_hull(hull: &[Vec<bool>]) -> String {
+    let mut output = String::new();
+    for row in hull {
+        for panel in row {
+            output += if *panel { "🦀" } else { "  " };
         }
+        output.push('\n');
     }
+    output
 }
 
-#[derive(Debug)]
-struct Instruction {
-    pub opcode: Opcode,
-    pub parameters: (Parameter, Parameter, Parameter),
-}
-
-impl Instruction {
-    pub fn fetch(ip: i64, memory: &Vec<i64>) -> Option<Self> {
-        let ip = ip as usize;
-        let instruction = memory.get(ip)?;
-
-        let opcode = Opcode::from(instruction % 100);
-        let parameters = (
-            Parameter::new(
-                ParameterMode::from(instruction / 100 % 10),
-                *memory.get(ip + 1).unwrap_or(&0),
-            ),
-            Parameter::new(
-                ParameterMode::from(instruction / 1000 % 10),
-                *memory.get(ip + 2).unwrap_or(&0),
-            ),
-            Parameter::new(
-                ParameterMode::from(instruction / 10000 % 10),
-                *memory.get(ip + 3).unwrap_or(&0),
-            ),
-        );
+// Every panel the robot stood on, in order, relative to the start, with the
+// colour it painted there. The panel it ends up on isn't painted.
+type Route = Vec<((i64, i64), Option<bool>)>;
+
+// Runs the robot on a hull that already has the panels in `initial` painted,
+// keyed by (x, y) relative to the start. Returns how many panels were painted
+// at least once, how many of those ended up white and black, and the letters
+// painted on the hull, if any. With `route`
+// the robot's route is recorded there instead of drawing the hull.
+fn run_robot<M: Machine>(
+    robot: &mut M,
+    initial: &HashMap<(i64, i64), bool>,
+    mut route: Option<&mut Route>,
+) -> Result<(usize, usize, usize, String), RobotError> {
+    let mut map = vec![vec![false; SIZE]; SIZE];
+    let mut cx = SIZE / 2;
+    let mut cy = SIZE / 2;
+    let mut direction = Direction::new();
+    for ((x, y), color) in initial {
+        map[(cy as i64 + y) as usize][(cx as i64 + x) as usize] = *color;
+    }
 
-        Some(Self { opcode, parameters })
+    let mut painted = HashSet::new();
+    let mut paint = None;
+
+    // Without ANSI the robot can't be followed, so the hull is drawn at the end.
+    let draw = route.is_none();
+    let ansi = draw && output::ansi();
+    let position =
+        |cx: usize, cy: usize| (cx as i64 - (SIZE / 2) as i64, cy as i64 - (SIZE / 2) as i64);
+    if ansi {
+        say!("\x1B[1;1H");
+        for _ in 0..SIZE {
+            sayln!("{:>1$}", "", SIZE * 2);
+        }
     }
-}
 
-struct Interpreter {
-    pub memory: Vec<i64>,
-    pub rx: Option<Receiver<i64>>,
-    pub tx: Option<Sender<i64>>,
-    pub last_output: Option<i64>,
-    pub ip: i64,
-    pub relative_base: i64,
-    pub debug: bool,
-}
+    // The robot starts by reading the colour of the panel it's standing on.
+    robot.provide_input(if map[cy][cx] { 1 } else { 0 });
+    while let Some(out) = robot.next_output()? {
+        // The robot always outputs a colour followed by a turn.
+        let color = match paint.take() {
+            None => {
+                paint = Some(out);
+                continue;
+            }
+            Some(color) => color == 1,
+        };
 
-impl Interpreter {
-    fn new(memory: &Vec<i64>) -> Self {
-        Self {
-            memory: memory.clone(),
-            rx: None,
-            tx: None,
-            last_output: None,
-            ip: 0,
-            relative_base: 0,
-            debug: false,
+        painted.insert((cx, cy));
+        map[cy][cx] = color;
+        if let Some(route) = &mut route {
+            route.push((position(cx, cy), Some(color)));
         }
-    }
 
-    pub fn step(&mut self) -> bool {
-        let instruction = Instruction::fetch(self.ip, &&self.memory).unwrap();
-        let (a, b, c) = &instruction.parameters;
-
-        if self.debug {
-            let args = format!("{:?} {}, {}, {}", instruction.opcode, a, b, c);
-            print!(
-                "ip={:<5} rb={:<5} | {:<30} | {:>5} -> ?        ",
-                self.ip,
-                self.relative_base,
-                args,
-                self.memory.len()
+        // println!("\x1B[1;1H{} {} {:?}                 ", cx, cy, direction);
+        if ansi {
+            sayln!(
+                "\x1B[1;1H\x1B[{}C\x1B[{}B{}\x1B[{}B",
+                cy * 2,
+                SIZE - cx,
+                if map[cy][cx] { "🦀" } else { "  " },
+                SIZE - cy,
             );
-            std::io::stdout().flush().unwrap();
         }
 
-        let (ip, arg_count) = match instruction.opcode {
-            Opcode::Add => {
-                *self.value_mut(&c) = self.value(&a) + self.value(&b);
-                (self.ip + 4, 3)
-            }
-
-            Opcode::Multiply => {
-                *self.value_mut(&c) = self.value(&a) * self.value(&b);
-                (self.ip + 4, 3)
-            }
+        match out {
+            0 => direction = direction.turn_left(),
+            1 => direction = direction.turn_right(),
+            _ => panic!("invalid turn {}", out),
+        };
 
-            Opcode::Read => {
-                *self.value_mut(&a) = match &self.rx {
-                    Some(rx) => {
-                        let input = rx.recv().unwrap();
-                        if self.debug {
-                            print!(">> {}", input);
-                        }
-                        input
-                    }
-                    None => {
-                        print!(">> ");
-                        std::io::stdout().flush().unwrap();
-                        try_read!().unwrap()
-                    }
-                };
-                (self.ip + 2, 1)
-            }
+        let (y, x) = direction.movement();
+        cx = (cx as i64 + x) as usize;
+        cy = (cy as i64 + y) as usize;
 
-            Opcode::Write => {
-                let value = self.value(&a);
-                self.last_output = Some(value);
-                match &self.tx {
-                    Some(tx) => {
-                        let _ = tx.send(value);
-                    }
-                    None => print!("<< {}", value),
-                }
-                (self.ip + 2, 1)
-            }
+        robot.provide_input(if map[cy][cx] { 1 } else { 0 });
+    }
 
-            Opcode::JumpIfTrue => (
-                if self.value(&a) != 0 {
-                    self.value(&b)
-                } else {
-                    self.ip + 3
-                },
-                2,
-            ),
+    if let Some(color) = paint {
+        return Err(RobotError::HaltedMidPair(color));
+    }
 
-            Opcode::JumpIfFalse => (
-                if self.value(&a) == 0 {
-                    self.value(&b)
-                } else {
-                    self.ip + 3
-                },
-                2,
-            ),
+    if let Some(route) = route {
+        route.push((position(cx, cy), None));
+    }
 
-            Opcode::LessThan => {
-                let result = self.value(&a) < self.value(&b);
-                *self.value_mut(&c) = if result { 1 } else { 0 };
-                (self.ip + 4, 3)
-            }
+    let hull = hull(&map, &painted);
+    if draw && !ansi {
+        say!("{}", render_hull(&hull));
+    }
+    let letters = ocr(&hull);
 
-            Opcode::Equals => {
-                let result = self.value(&a) == self.value(&b);
-                *self.value_mut(&c) = if result { 1 } else { 0 };
-                (self.ip + 4, 3)
-            }
+    let white = painted.iter().filter(|(x, y)| map[*y][*x]).count();
+    Ok((painted.len(), white, painted.len() - white, letters))
+}
 
-            Opcode::RelativeBase => {
-                self.relative_base += self.value(&a);
-                (self.ip + 2, 1)
-            }
+// An interpreter for the robot's program that leaves its outputs to the driver.
+fn robot(memory: &[i64]) -> Interpreter {
+    let mut interpreter = Interpreter::new(memory);
+    interpreter.echo_output = false;
+    interpreter
+}
 
-            Opcode::Halt => {
-                return false;
-            }
-        };
+fn part1(memory: &[i64]) -> Result<(usize, usize, usize, String), RobotError> {
+    run_robot(&mut robot(memory), &HashMap::new(), None)
+}
 
-        if self.debug {
-            let args = match arg_count {
-                1 => format!("{:?} {}", instruction.opcode, a),
-                2 => format!("{:?} {}, {}", instruction.opcode, a, b),
-                3 => format!("{:?} {}, {}, {}", instruction.opcode, a, b, c),
-                _ => panic!(),
-            };
+// Starting on a white panel, the robot paints the registration identifier.
+fn part2(memory: &[i64]) -> Result<String, RobotError> {
+    let initial = [((0, 0), true)].iter().cloned().collect();
+    let (_, _, _, letters) = run_robot(&mut robot(memory), &initial, None)?;
+    Ok(letters)
+}
 
-            if instruction.opcode == Opcode::Read {
-                print!("\x1B[1A");
+fn main() -> Result<(), RobotError> {
+    let mut filename = None;
+    let mut initial = HashMap::new();
+    let mut show_route = false;
+
+    // `--white X,Y` starts the robot with that panel already painted white.
+    // `--route` prints where the robot went instead of drawing the hull.
+    let mut args = output::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--white" => {
+                let panel = args.next().expect("--white needs a panel");
+                let mut coords = panel.split(',').map(|coord| coord.parse().unwrap());
+                initial.insert((coords.next().unwrap(), coords.next().unwrap()), true);
             }
-
-            println!(
-                "\rip=\x1B[5C rb=\x1B[5C | {:<30} | \x1B[5C -> {:?}",
-                args,
-                self.memory.len()
-            );
+            "--route" => show_route = true,
+            _ => filename = Some(arg),
         }
+    }
 
-        self.ip = ip;
+    let memory = input::read_program(filename)?;
 
-        true
+    if show_route {
+        let mut route = Route::new();
+        run_robot(&mut robot(&memory), &initial, Some(&mut route))?;
+        for ((x, y), color) in route {
+            match color {
+                Some(true) => println!("{},{} white", x, y),
+                Some(false) => println!("{},{} black", x, y),
+                None => println!("{},{}", x, y),
+            }
+        }
+        return Ok(());
     }
 
-    pub fn run(&mut self) {
-        while self.step() {}
+    let now = Instant::now();
+    if !initial.is_empty() {
+        let (painted, white, black, letters) = run_robot(&mut robot(&memory), &initial, None)?;
+        sayln!();
+        sayln!("painted: {}, white: {}, black: {}", painted, white, black);
+        if !letters.is_empty() {
+            sayln!("letters: {}", letters);
+        }
+        sayln!("{:?}", now.elapsed());
+        return Ok(());
     }
 
-    fn value(&self, parameter: &Parameter) -> i64 {
-        let index = match parameter.mode {
-            ParameterMode::Position => parameter.value as usize,
-            ParameterMode::Relative => (parameter.value + self.relative_base) as usize,
-            ParameterMode::Immediate => {
-                return parameter.value;
-            }
-        };
+    let (part1, white, black, _) = part1(&memory)?;
+    sayln!();
+    answer(1, part1);
+    sayln!("white: {}, black: {}", white, black);
+    answer(2, part2(&memory)?);
+    sayln!("{:?}", now.elapsed());
 
-        *self.memory.get(index).unwrap_or(&0)
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aoc2019::intcode::machine::{ScriptedMachine, StepResult};
+
+    // The robot from the puzzle's example, as (colour, turn) pairs.
+    fn example() -> ScriptedMachine {
+        let pairs = [(1, 0), (0, 0), (1, 0), (1, 0), (0, 1), (1, 0), (1, 0)];
+        ScriptedMachine::new(
+            pairs
+                .iter()
+                .flat_map(|(color, turn)| {
+                    vec![StepResult::Output(*color), StepResult::Output(*turn)]
+                })
+                .collect(),
+        )
     }
 
-    fn value_mut<'a>(&'a mut self, parameter: &Parameter) -> &'a mut i64 {
-        let index = usize::try_from(match parameter.mode {
-            ParameterMode::Position => parameter.value,
-            ParameterMode::Relative => (parameter.value + self.relative_base),
-            ParameterMode::Immediate => panic!("can't get immediate as mut"),
-        })
-        .unwrap();
-
-        if index >= self.memory.len() {
-            if self.debug {
-                println!("resizing memory to {}", index);
-            }
-            self.memory.resize(index + 1, 0);
-        }
+    #[test]
+    fn example_counts() {
+        let mut route = Route::new();
+        let (painted, white, black, _) =
+            run_robot(&mut example(), &HashMap::new(), Some(&mut route)).unwrap();
+        assert_eq!((painted, white, black), (6, 4, 2));
+    }
 
-        self.memory.get_mut(index).unwrap()
+    #[test]
+    fn example_route() {
+        // The hull is stored transposed, so turning left from the start moves
+        // along the first coordinate rather than the second.
+        let mut route = Route::new();
+        run_robot(&mut example(), &HashMap::new(), Some(&mut route)).unwrap();
+        assert_eq!(
+            route,
+            vec![
+                ((0, 0), Some(true)),
+                ((0, -1), Some(false)),
+                ((-1, -1), Some(true)),
+                ((-1, 0), Some(true)),
+                ((0, 0), Some(false)),
+                ((0, 1), Some(true)),
+                ((1, 1), Some(true)),
+                ((1, 0), None),
+            ]
+        );
     }
-}
 
-fn part1(memory: &Vec<i64>) -> usize {
-    let mut interpreter = Interpreter::new(memory);
-    let (tx_input, rx_input) = channel();
-    let (tx_output, rx_output) = channel();
-    interpreter.rx = Some(rx_input);
-    interpreter.tx = Some(tx_output);
-
-    let mut map = vec![vec![false; 100]; 100];
-    let mut cx = 50usize;
-    let mut cy = 50usize;
-    let mut direction = Direction::new();
+    #[test]
+    fn seeded_panels_are_read() {
+        // Paints the first panel white, turns left and stops.
+        let script = vec![StepResult::Output(1), StepResult::Output(0)];
+        let mut route = Route::new();
 
-    let mut painted = HashSet::new();
-    let mut turned = true;
+        let mut robot = ScriptedMachine::new(script.clone());
+        run_robot(&mut robot, &HashMap::new(), Some(&mut route)).unwrap();
+        assert_eq!(robot.inputs, vec![0, 0]);
 
-    print!("\x1B[1;1H");
-    for _ in 0..100 {
-        println!("{:>200}", "");
+        let initial = [((0, 0), true), ((0, -1), true)].iter().cloned().collect();
+        let mut robot = ScriptedMachine::new(script);
+        run_robot(&mut robot, &initial, Some(&mut route)).unwrap();
+        assert_eq!(robot.inputs, vec![1, 1]);
     }
 
-    tx_input.send(1).unwrap();
-    while interpreter.step() {
-        if let Ok(out) = rx_output.try_recv() {
-            if !turned {
-                match out {
-                    0 => direction = direction.turn_left(),
-                    1 => direction = direction.turn_right(),
-                    _ => panic!("invalid turn {}", out),
-                };
-
-                let (y, x) = direction.movement();
-                cx = (cx as i64 + x) as usize;
-                cy = (cy as i64 + y) as usize;
-
-                tx_input.send(if map[cy][cx] { 1 } else { 0 }).unwrap();
-            } else {
-                let color = out == 1;
-                painted.insert((cx, cy));
-                map[cy][cx] = color;
-
-                // println!("\x1B[1;1H{} {} {:?}                 ", cx, cy, direction);
-                println!(
-                    "\x1B[1;1H\x1B[{}C\x1B[{}B{}\x1B[{}B",
-                    cy * 2,
-                    100 - cx,
-                    if map[cy][cx] { "🦀" } else { "  " },
-                    100 - cy,
-                );
-            }
+    #[test]
+    fn part1_starts_on_black() {
+        // Paints the panel the colour it reads, turns left and halts.
+        let memory = vec![3, 100, 4, 100, 104, 0, 99];
+        let (painted, white, black, _) = part1(&memory).unwrap();
+        assert_eq!((painted, white, black), (1, 0, 1));
+    }
 
-            turned = !turned;
+    #[test]
+    fn letters_on_hull() {
+        let rows = [
+            "#..#.#..#",
+            "#..#.#.#.",
+            "####.##..",
+            "#..#.#.#.",
+            "#..#.#.#.",
+            "#..#.#..#",
+        ];
+
+        // Paints the letters the way round the robot does: its x runs up the
+        // rows and its y along them.
+        let mut map = vec![vec![false; SIZE]; SIZE];
+        let mut painted = HashSet::new();
+        for (row, pixels) in rows.iter().enumerate() {
+            for (y, pixel) in pixels.chars().enumerate() {
+                let x = rows.len() - 1 - row;
+                map[y][x] = pixel == '#';
+                painted.insert((x, y));
+            }
         }
+        assert_eq!(ocr(&hull(&map, &painted)), "HK");
     }
 
-    painted.len()
-}
-
-fn main() {
-    let file = File::open(std::env::args().nth(1).expect("no filename provided")).unwrap();
-    let mut input = String::new();
-    BufReader::new(file).read_line(&mut input).unwrap();
-
-    let memory: Vec<i64> = input
-        .split(",")
-        .map(|x| x.trim().parse().unwrap())
-        .collect();
-
-    let now = Instant::now();
-    println!(
-        "\nPART 1: {}                                       ",
-        part1(&memory)
-    );
-    dbg!(now.elapsed());
+    #[test]
+    fn halted_mid_pair() {
+        // Reads the panel, paints it white and halts without turning.
+        let memory = vec![3, 100, 104, 1, 99];
+        assert!(matches!(
+            run_robot(&mut robot(&memory), &HashMap::new(), None),
+            Err(RobotError::HaltedMidPair(1))
+        ));
+    }
 }
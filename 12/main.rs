@@ -1,7 +1,10 @@
+use aoc2019::input::{self, InputError};
+use aoc2019::math::lcm_all;
+use aoc2019::output::{self, answer};
 use regex::Regex;
-use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::io::BufRead;
+use std::thread;
 
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
 struct Point {
@@ -14,6 +17,10 @@ impl Point {
     fn energy(&self) -> i64 {
         self.x.abs() + self.y.abs() + self.z.abs()
     }
+
+    fn to_json(&self) -> String {
+        format!(r#"{{"x":{},"y":{},"z":{}}}"#, self.x, self.y, self.z)
+    }
 }
 
 impl Display for Point {
@@ -33,6 +40,22 @@ struct Moon {
 }
 
 impl Moon {
+    fn position(&self) -> &Point {
+        &self.pos
+    }
+
+    fn velocity(&self) -> &Point {
+        &self.vel
+    }
+
+    fn to_json(&self) -> String {
+        format!(
+            r#"{{"position":{},"velocity":{}}}"#,
+            self.pos.to_json(),
+            self.vel.to_json()
+        )
+    }
+
     fn update_vel(&self, other: &Self) -> Point {
         let mut vel = self.vel.clone();
         if self.pos.x < other.pos.x {
@@ -68,12 +91,10 @@ impl Display for Moon {
     }
 }
 
-fn read_input() -> Vec<Moon> {
-    let stdin = std::io::stdin();
+fn parse_moons(reader: impl BufRead) -> Vec<Moon> {
     let re = Regex::new(r"<x=([\d\-]+), y=([\d\-]+), z=([\d\-]+)>").unwrap();
 
-    stdin
-        .lock()
+    reader
         .lines()
         .map(|line| {
             let line = line.unwrap();
@@ -90,12 +111,26 @@ fn read_input() -> Vec<Moon> {
         .collect()
 }
 
-fn part1(moons: &mut Vec<Moon>) -> i64 {
-    const steps: usize = 1000;
+fn dump_table(moons: &[Moon]) -> String {
+    let mut table = format!("{:>4}  {:<21}  {}\n", "moon", "position", "velocity");
+    for (i, moon) in moons.iter().enumerate() {
+        let position = moon.position().to_string();
+        let velocity = moon.velocity().to_string();
+        table += &format!("{:>4}  {:<21}  {}\n", i, position, velocity);
+    }
+    table
+}
+
+fn to_json(moons: &[Moon]) -> String {
+    let moons: Vec<_> = moons.iter().map(Moon::to_json).collect();
+    format!("[{}]", moons.join(","))
+}
+
+fn part1(moons: &mut [Moon], steps: usize, verbose: bool) -> i64 {
     for ts in 0..steps {
-        println!("\nAfter {} steps:", ts);
-        for moon in moons.iter() {
-            println!("{}", moon);
+        if verbose {
+            println!("\nAfter {} steps:", ts);
+            print!("{}", dump_table(moons));
         }
 
         for b in 0..moons.len() {
@@ -113,81 +148,194 @@ fn part1(moons: &mut Vec<Moon>) -> i64 {
         }
     }
 
-    println!("\nAfter {} steps:", steps);
-    for moon in moons.iter() {
-        println!("{}", moon);
+    if verbose {
+        println!("\nAfter {} steps:", steps);
+        print!("{}", dump_table(moons));
+        println!("\nEnergy:");
     }
 
-    println!("\nEnergy:");
     moons
         .iter()
         .map(|moon| {
-            let pot = moon.pos.energy();
-            let kin = moon.vel.energy();
+            let pot = moon.position().energy();
+            let kin = moon.velocity().energy();
             let total = pot * kin;
-            println!("pot: {:>5}; kin: {:>5}; total: {:>5}", pot, kin, total);
+            if verbose {
+                println!("pot: {:>5}; kin: {:>5}; total: {:>5}", pot, kin, total);
+            }
             total
         })
         .sum()
 }
 
-fn part2(moons: &mut Vec<Moon>) -> i64 {
-    let mut sets: Vec<_> = (0..moons.len()).map(|_| HashMap::new()).collect();
-    let mut cycle_lengths: Vec<_> = (0..moons.len()).map(|_| None).collect();
+// Steps until the moons return to their initial state along a single axis.
+fn axis_period(moons: &[Moon], axis: fn(&Point) -> i64) -> u64 {
+    let initial: Vec<_> = moons
+        .iter()
+        .map(|moon| (axis(&moon.pos), axis(&moon.vel)))
+        .collect();
+    let mut state = initial.clone();
 
-    let mut ts = 0usize;
+    let mut steps = 0;
     loop {
-        for b in 0..moons.len() {
-            for a in 0..moons.len() {
-                if a == b {
-                    continue;
+        for a in 0..state.len() {
+            for b in 0..state.len() {
+                if state[a].0 < state[b].0 {
+                    state[a].1 += 1;
+                } else if state[a].0 > state[b].0 {
+                    state[a].1 -= 1;
                 }
-                moons[a].vel = moons[a].update_vel(&moons[b]);
             }
         }
-        for moon in moons.iter_mut() {
-            moon.update_pos();
+        for (pos, vel) in state.iter_mut() {
+            *pos += *vel;
         }
 
-        let mut has_length = 0;
-        for (i, moon) in moons.iter().enumerate() {
-            if cycle_lengths[i].is_some() {
-                has_length += 1;
-                continue;
-            }
-
-            if let Some(old_ts) = sets[i].insert(moon.clone(), ts) {
-                cycle_lengths[i] = Some((old_ts, ts - old_ts));
-            }
+        steps += 1;
+        if state == initial {
+            return steps;
         }
+    }
+}
 
-        if ts % 1000000 == 0 {
-            println!("{} {}     \r", ts, has_length);
-        }
-        if has_length == moons.len() {
-            break;
-        }
+const AXES: [fn(&Point) -> i64; 3] = [|point| point.x, |point| point.y, |point| point.z];
+
+// The axes don't affect each other, so with `parallel` each one gets its own
+// thread.
+fn part2(moons: &[Moon], parallel: bool) -> u64 {
+    let periods: Vec<u64> = if parallel {
+        let threads: Vec<_> = AXES
+            .iter()
+            .map(|&axis| {
+                let moons = moons.to_vec();
+                thread::spawn(move || axis_period(&moons, axis))
+            })
+            .collect();
+        threads
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .collect()
+    } else {
+        AXES.iter().map(|&axis| axis_period(moons, axis)).collect()
+    };
+
+    lcm_all(&periods).unwrap()
+}
 
-        ts += 1;
+fn main() -> Result<(), InputError> {
+    let mut filename = None;
+    let mut steps = 1000;
+    let mut skip_part2 = false;
+    let mut verbose = false;
+    let mut json = false;
+    let mut parallel = false;
+
+    let mut args = output::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--steps" => steps = args.next().expect("--steps needs a value").parse().unwrap(),
+            "--skip-part2" => skip_part2 = true,
+            "--verbose" => verbose = true,
+            "--json" => json = true,
+            "--parallel" => parallel = true,
+            _ => filename = Some(arg),
+        }
     }
 
-    dbg!(cycle_lengths);
+    let moons = parse_moons(input::reader(filename)?);
+    let verbose = verbose && !output::quiet();
+    let mut simulated = moons.clone();
+    answer(1, part1(&mut simulated, steps, verbose));
+    if json {
+        println!("{}", to_json(&simulated));
+    }
+    if !skip_part2 {
+        answer(2, part2(&moons, parallel));
+    }
 
-    0
+    Ok(())
 }
 
-fn main() {
-    let moons = read_input();
-    dbg!(part1(&mut moons.clone()));
-    dbg!(part2(&mut moons.clone()));
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE1: &str = "<x=-1, y=0, z=2>
+<x=2, y=-10, z=-7>
+<x=4, y=-8, z=8>
+<x=3, y=5, z=-1>
+";
+
+    const EXAMPLE2: &str = "<x=-8, y=-10, z=0>
+<x=5, y=5, z=10>
+<x=2, y=-7, z=3>
+<x=9, y=-8, z=-3>
+";
 
-    // let cycle_lengths = [
-    //     Some((23446604, 2010370)),
-    //     Some((3247283, 16599261)),
-    //     Some((5033188, 10043143)),
-    //     Some((834120, 11533736)),
-    // ];
-    // for (start, length) in cycle_lengths {
+    // Steps until every position and velocity is back where it started. Far too
+    // slow for the real input.
+    fn brute_cycle(moons: &[Moon]) -> u64 {
+        let mut state = moons.to_vec();
+        let mut steps = 0;
+        loop {
+            part1(&mut state, 1, false);
+            steps += 1;
+            if state == moons {
+                return steps;
+            }
+        }
+    }
 
-    // }
+    #[test]
+    fn brute_force_period() {
+        let moons = parse_moons(EXAMPLE1.as_bytes());
+        assert_eq!(brute_cycle(&moons), 2772);
+        assert_eq!(part2(&moons, false), 2772);
+        assert_eq!(part2(&moons, true), 2772);
+    }
+
+    #[test]
+    fn parallel_matches_serial() {
+        for (example, period) in &[(EXAMPLE1, 2772), (EXAMPLE2, 4686774924)] {
+            let moons = parse_moons(example.as_bytes());
+            assert_eq!(part2(&moons, false), *period);
+            assert_eq!(part2(&moons, true), *period);
+        }
+    }
+
+    #[test]
+    fn table_rows() {
+        let table = dump_table(&parse_moons(EXAMPLE1.as_bytes()));
+        let mut lines = table.lines();
+        assert_eq!(lines.next(), Some("moon  position               velocity"));
+        assert_eq!(
+            lines.next(),
+            Some("   0  <x= -1, y=  0, z=  2>  <x=  0, y=  0, z=  0>")
+        );
+        assert_eq!(lines.count(), 3);
+    }
+
+    #[test]
+    fn part1_custom_steps() {
+        assert_eq!(part1(&mut parse_moons(EXAMPLE1.as_bytes()), 10, false), 179);
+        assert_eq!(
+            part1(&mut parse_moons(EXAMPLE2.as_bytes()), 100, false),
+            1940
+        );
+    }
+
+    #[test]
+    fn json_after_two_steps() {
+        let mut moons = parse_moons(EXAMPLE1.as_bytes());
+        part1(&mut moons, 2, false);
+        assert_eq!(
+            to_json(&moons),
+            concat!(
+                r#"[{"position":{"x":5,"y":-3,"z":-1},"velocity":{"x":3,"y":-2,"z":-2}},"#,
+                r#"{"position":{"x":1,"y":-2,"z":2},"velocity":{"x":-2,"y":5,"z":6}},"#,
+                r#"{"position":{"x":1,"y":-4,"z":-1},"velocity":{"x":0,"y":3,"z":-6}},"#,
+                r#"{"position":{"x":1,"y":-4,"z":2},"velocity":{"x":-1,"y":-6,"z":2}}]"#,
+            )
+        );
+    }
 }
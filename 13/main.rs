@@ -1,9 +1,16 @@
-use std::convert::{From, TryFrom};
-use std::fmt::{Display, Formatter};
-use std::fs::File;
-use std::io::{BufRead, BufReader, Write};
-use std::sync::mpsc::{channel, Receiver, Sender};
-use text_io::{try_read, try_scan};
+use aoc2019::hash::fnv1a;
+use aoc2019::input::{self, InputError};
+use aoc2019::intcode::interpreter::{IntcodeError, Interpreter, Snapshot};
+use aoc2019::intcode::machine::Machine;
+use aoc2019::map::{render_grid, Renderable};
+use aoc2019::output::{self, answer};
+use aoc2019::sayln;
+use std::convert::From;
+use std::fmt::{Debug, Display, Formatter};
+use std::io::Read;
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 enum Tile {
@@ -26,421 +33,693 @@ impl From<i64> for Tile {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum Opcode {
-    Add,
-    Multiply,
-    Read,
-    Write,
-    JumpIfTrue,
-    JumpIfFalse,
-    LessThan,
-    Equals,
-    RelativeBase,
-    Halt,
+impl Renderable for Tile {
+    fn glyph(&self) -> &str {
+        match self {
+            Tile::Empty => "  ",
+            Tile::Ball => "⬤ ",
+            Tile::Block => "█▉",
+            Tile::HorizontalPaddle => "▀▀",
+            Tile::Wall => "██",
+        }
+    }
 }
 
-impl From<i64> for Opcode {
-    fn from(item: i64) -> Self {
-        match item {
-            1 => Opcode::Add,
-            2 => Opcode::Multiply,
-            3 => Opcode::Read,
-            4 => Opcode::Write,
-            5 => Opcode::JumpIfTrue,
-            6 => Opcode::JumpIfFalse,
-            7 => Opcode::LessThan,
-            8 => Opcode::Equals,
-            9 => Opcode::RelativeBase,
-            99 => Opcode::Halt,
-            _ => panic!("unknown instruction {}", item),
-        }
+enum GameError {
+    Input(InputError),
+    Intcode(IntcodeError),
+    // The program halted or wanted input partway through drawing a tile.
+    StoppedMidTile,
+}
+
+impl From<InputError> for GameError {
+    fn from(error: InputError) -> Self {
+        GameError::Input(error)
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum ParameterMode {
-    Position,
-    Immediate,
-    Relative,
+impl From<IntcodeError> for GameError {
+    fn from(error: IntcodeError) -> Self {
+        GameError::Intcode(error)
+    }
 }
 
-impl From<i64> for ParameterMode {
-    fn from(item: i64) -> Self {
-        match item {
-            0 => ParameterMode::Position,
-            1 => ParameterMode::Immediate,
-            2 => ParameterMode::Relative,
-            _ => panic!("unknown parameter mode {}", item),
+impl Display for GameError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            GameError::Input(error) => write!(formatter, "{}", error),
+            GameError::Intcode(error) => write!(formatter, "{}", error),
+            GameError::StoppedMidTile => {
+                write!(formatter, "program stopped partway through a tile")
+            }
         }
     }
 }
 
-#[derive(Debug)]
-struct Parameter {
-    pub mode: ParameterMode,
-    pub value: i64,
+impl Debug for GameError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        Display::fmt(self, formatter)
+    }
 }
 
-impl Parameter {
-    fn new(mode: ParameterMode, value: i64) -> Self {
-        Self { mode, value }
+// Returns the number of blocks and the board's width and height, which are
+// worked out from the tiles drawn.
+fn part1(memory: &[i64]) -> Result<(usize, (usize, usize)), GameError> {
+    let mut interpreter = Interpreter::new(memory);
+    interpreter.echo_output = false;
+    interpreter.push_input(1);
+
+    let mut map = Vec::new();
+    let mut score = 0;
+    while let Some(x) = interpreter.run_until_output()? {
+        let y = interpreter
+            .run_until_output()?
+            .ok_or(GameError::StoppedMidTile)?;
+        let out = interpreter
+            .run_until_output()?
+            .ok_or(GameError::StoppedMidTile)?;
+        if x == -1 && y == 0 {
+            score = out;
+            continue;
+        }
+
+        place(&mut map, x, y, Tile::from(out));
     }
+    draw_map(&map, score, Duration::default());
+
+    let width = map.first().map_or(0, |row| row.len());
+    let blocks = map
+        .iter()
+        .map(|row| row.iter().filter(|tile| **tile == Tile::Block).count())
+        .sum();
+    Ok((blocks, (width, map.len())))
 }
 
-impl Display for Parameter {
-    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
-        match self.mode {
-            ParameterMode::Immediate => write!(formatter, "{}", self.value),
-            ParameterMode::Position => write!(formatter, "[{}]", self.value),
-            ParameterMode::Relative => write!(formatter, "rel[{}]", self.value),
+// Collects tiles until the machine stops outputting, growing the board to fit
+// them. Returns the board and the last score drawn.
+fn read_board<M: Machine>(machine: &mut M) -> Result<(Vec<Vec<Tile>>, i64), GameError> {
+    let mut map = Vec::new();
+    let mut score = 0;
+
+    while let Some(x) = machine.next_output()? {
+        let y = expect_output(machine)?;
+        let out = expect_output(machine)?;
+        if x == -1 && y == 0 {
+            score = out;
+            continue;
+        }
+
+        place(&mut map, x, y, Tile::from(out));
+    }
+
+    Ok((map, score))
+}
+
+// Puts a tile on the board, growing it first if the tile falls outside.
+fn place(map: &mut Vec<Vec<Tile>>, x: i64, y: i64, tile: Tile) {
+    let (x, y) = (x as usize, y as usize);
+    let width = map.first().map_or(0, |row| row.len());
+    if x >= width {
+        for row in map.iter_mut() {
+            row.resize(x + 1, Tile::Empty);
         }
     }
+    if y >= map.len() {
+        map.resize(y + 1, vec![Tile::Empty; width.max(x + 1)]);
+    }
+
+    map[y][x] = tile;
 }
 
-#[derive(Debug)]
-struct Instruction {
-    pub opcode: Opcode,
-    pub parameters: (Parameter, Parameter, Parameter),
+// Waits `frame` after drawing so the game can be watched.
+fn draw_map(map: &[Vec<Tile>], score: i64, frame: Duration) {
+    if output::quiet() {
+        return;
+    }
+
+    print!("{}{}", output::home(), render_grid(map));
+    println!("score: {}", score);
+    thread::sleep(frame);
 }
 
-impl Instruction {
-    pub fn fetch(ip: i64, memory: &Vec<i64>) -> Option<Self> {
-        let ip = ip as usize;
-        let instruction = memory.get(ip)?;
-
-        let opcode = Opcode::from(instruction % 100);
-        let parameters = (
-            Parameter::new(
-                ParameterMode::from(instruction / 100 % 10),
-                *memory.get(ip + 1).unwrap_or(&0),
-            ),
-            Parameter::new(
-                ParameterMode::from(instruction / 1000 % 10),
-                *memory.get(ip + 2).unwrap_or(&0),
-            ),
-            Parameter::new(
-                ParameterMode::from(instruction / 10000 % 10),
-                *memory.get(ip + 3).unwrap_or(&0),
-            ),
-        );
+// -1 moves the paddle left, 1 moves it right, 0 keeps it where it is.
+fn joystick_toward(paddle_x: i64, ball_x: i64) -> i64 {
+    (ball_x - paddle_x).signum()
+}
 
-        Some(Self { opcode, parameters })
+// Space pauses and resumes the game, q quits. Blocks for as long as the game is
+// paused. Returns whether the game should stop.
+fn should_quit(keys: &Receiver<u8>) -> bool {
+    let mut paused = false;
+    loop {
+        let key = if paused {
+            keys.recv().ok()
+        } else {
+            keys.try_recv().ok()
+        };
+
+        match key {
+            Some(b' ') => paused = !paused,
+            Some(b'q') => return true,
+            Some(_) => {}
+            None => return false,
+        }
     }
 }
 
-struct Interpreter {
-    pub memory: Vec<i64>,
-    pub rx: Option<Receiver<i64>>,
-    pub tx: Option<Sender<i64>>,
-    pub last_output: Option<i64>,
-    pub ip: i64,
-    pub relative_base: i64,
-    pub debug: bool,
+// A finished game: a hash of the program it was played on, the final score and
+// every joystick input in order. Saved as a `program <hash>` line, a
+// `score <score>` line and then one input per line.
+struct Replay {
+    program: u64,
+    score: i64,
+    inputs: Vec<i64>,
 }
+impl Replay {
+    fn parse(text: &str) -> Self {
+        let mut lines = text.lines();
+        let mut field = |name: &str| {
+            let line = lines.next().expect("replay is truncated");
+            if !line.starts_with(name) {
+                panic!("expected {} line in replay, got {:?}", name, line);
+            }
+            line[name.len()..].trim().to_string()
+        };
+
+        let program = u64::from_str_radix(&field("program"), 16).unwrap();
+        let score = field("score").parse().unwrap();
+        let inputs = lines.map(|line| line.parse().unwrap()).collect();
 
-impl Interpreter {
-    fn new(memory: &Vec<i64>) -> Self {
         Self {
-            memory: memory.clone(),
-            rx: None,
-            tx: None,
-            last_output: None,
-            ip: 0,
-            relative_base: 0,
-            debug: false,
+            program,
+            score,
+            inputs,
         }
     }
-
-    pub fn step(&mut self) -> bool {
-        let instruction = Instruction::fetch(self.ip, &&self.memory).unwrap();
-        let (a, b, c) = &instruction.parameters;
-
-        if self.debug {
-            let args = format!("{:?} {}, {}, {}", instruction.opcode, a, b, c);
-            print!(
-                "ip={:<5} rb={:<5} | {:<30} | {:>5} -> ?        ",
-                self.ip,
-                self.relative_base,
-                args,
-                self.memory.len()
-            );
-            std::io::stdout().flush().unwrap();
+}
+impl Display for Replay {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        writeln!(f, "program {:016x}", self.program)?;
+        writeln!(f, "score {}", self.score)?;
+        for input in &self.inputs {
+            writeln!(f, "{}", input)?;
         }
+        Ok(())
+    }
+}
 
-        let (ip, arg_count) = match instruction.opcode {
-            Opcode::Add => {
-                *self.value_mut(&c) = self.value(&a) + self.value(&b);
-                (self.ip + 4, 3)
-            }
+fn program_hash(memory: &[i64]) -> u64 {
+    fnv1a(memory.iter().flat_map(|word| word.to_le_bytes().to_vec()))
+}
 
-            Opcode::Multiply => {
-                *self.value_mut(&c) = self.value(&a) * self.value(&b);
-                (self.ip + 4, 3)
-            }
+// Plays the game from `state`, steering towards the ball unless `replay` gives
+// the joystick inputs to use. Returns the state the game ended in, with how
+// many frames were played and how long steering took, and the inputs that were
+// sent.
+fn play<M: Machine>(
+    machine: &mut M,
+    mut state: GameState,
+    keys: &Receiver<u8>,
+    replay: Option<&[i64]>,
+    frame: Duration,
+) -> Result<(GameState, Vec<i64>), GameError> {
+    let mut replay = replay.map(|inputs| inputs.iter());
+    let mut inputs = Vec::new();
+
+    loop {
+        match machine.next_output()? {
+            Some(x) => {
+                let y = expect_output(machine)?;
+                let out = expect_output(machine)?;
+                if x == -1 && y == 0 {
+                    state.score = out;
+                    continue;
+                }
 
-            Opcode::Read => {
-                *self.value_mut(&a) = match &self.rx {
-                    Some(rx) => {
-                        let input = rx.recv().unwrap();
-                        if self.debug {
-                            print!(">> {}", input);
-                        }
-                        while rx.try_recv().is_ok() {}
-                        input
-                    }
-                    None => {
-                        print!(">> ");
-                        std::io::stdout().flush().unwrap();
-                        try_read!().unwrap()
-                    }
-                };
-                (self.ip + 2, 1)
+                state.draw(x, y, Tile::from(out));
             }
 
-            Opcode::Write => {
-                let value = self.value(&a);
-                self.last_output = Some(value);
-                match &self.tx {
-                    Some(tx) => {
-                        let _ = tx.send(value);
-                    }
-                    None => print!("<< {}", value),
+            None if machine.is_halted() => return Ok((state, inputs)),
+
+            // The game reads the joystick once per frame.
+            None => {
+                draw_map(&state.board, state.score, frame);
+                if should_quit(keys) {
+                    return Ok((state, inputs));
                 }
-                (self.ip + 2, 1)
-            }
 
-            Opcode::JumpIfTrue => (
-                if self.value(&a) != 0 {
-                    self.value(&b)
-                } else {
-                    self.ip + 3
-                },
-                2,
-            ),
-
-            Opcode::JumpIfFalse => (
-                if self.value(&a) == 0 {
-                    self.value(&b)
-                } else {
-                    self.ip + 3
-                },
-                2,
-            ),
-
-            Opcode::LessThan => {
-                let result = self.value(&a) < self.value(&b);
-                *self.value_mut(&c) = if result { 1 } else { 0 };
-                (self.ip + 4, 3)
+                let now = Instant::now();
+                let input = match &mut replay {
+                    Some(replay) => *replay.next().expect("replay ran out of inputs"),
+                    None => joystick_toward(state.paddle, state.ball),
+                };
+                state.deciding += now.elapsed();
+                state.frames += 1;
+                machine.provide_input(input);
+                inputs.push(input);
             }
+        }
+    }
+}
 
-            Opcode::Equals => {
-                let result = self.value(&a) == self.value(&b);
-                *self.value_mut(&c) = if result { 1 } else { 0 };
-                (self.ip + 4, 3)
-            }
+// Tiles are drawn as x, y, tile triples, so the rest of the triple has to follow.
+fn expect_output<M: Machine>(machine: &mut M) -> Result<i64, GameError> {
+    machine.next_output()?.ok_or(GameError::StoppedMidTile)
+}
 
-            Opcode::RelativeBase => {
-                self.relative_base += self.value(&a);
-                (self.ip + 2, 1)
-            }
+// Everything the steering needs to know about the game, kept between frames.
+#[derive(Default)]
+struct GameState {
+    board: Vec<Vec<Tile>>,
+    blocks: usize,
+    paddle: i64,
+    ball: i64,
+    score: i64,
+    // How many times the joystick was read, and how long it took to decide
+    // where to move it.
+    frames: usize,
+    deciding: Duration,
+}
 
-            Opcode::Halt => {
-                return false;
-            }
-        };
+impl GameState {
+    fn report_frames(&self) {
+        sayln!(
+            "frames: {}, deciding: {:?} per frame",
+            self.frames,
+            self.deciding / self.frames.max(1) as u32
+        );
+    }
 
-        if self.debug {
-            let args = match arg_count {
-                1 => format!("{:?} {}", instruction.opcode, a),
-                2 => format!("{:?} {}, {}", instruction.opcode, a, b),
-                3 => format!("{:?} {}, {}, {}", instruction.opcode, a, b, c),
-                _ => panic!(),
-            };
+    // Puts a tile on the board, keeping track of the paddle, the ball and how
+    // many blocks are left.
+    fn draw(&mut self, x: i64, y: i64, tile: Tile) {
+        match tile {
+            Tile::HorizontalPaddle => self.paddle = x,
+            Tile::Ball => self.ball = x,
+            _ => {}
+        }
 
-            if instruction.opcode == Opcode::Read {
-                print!("\x1B[1A");
-            }
+        let old = self
+            .board
+            .get(y as usize)
+            .and_then(|row| row.get(x as usize));
+        match (old == Some(&Tile::Block), tile == Tile::Block) {
+            (true, false) => self.blocks -= 1,
+            (false, true) => self.blocks += 1,
+            _ => {}
+        }
+        place(&mut self.board, x, y, tile);
+    }
+}
 
-            println!(
-                "\rip=\x1B[5C rb=\x1B[5C | {:<30} | \x1B[5C -> {:?}",
-                args,
-                self.memory.len()
-            );
+// Sets up the game with quarters inserted and runs it until it first reads the
+// joystick, by which time the whole board has been drawn. Returns the game so
+// far and the interpreter at that point, to resume playing from.
+fn read_initial_board(memory: &[i64]) -> Result<(GameState, Snapshot), GameError> {
+    let mut interpreter = free_play(memory);
+    let (board, score) = read_board(&mut interpreter)?;
+
+    let mut state = GameState {
+        score,
+        ..GameState::default()
+    };
+    for row in &board {
+        for (x, tile) in row.iter().enumerate() {
+            match tile {
+                Tile::Block => state.blocks += 1,
+                Tile::HorizontalPaddle => state.paddle = x as i64,
+                Tile::Ball => state.ball = x as i64,
+                _ => {}
+            }
         }
+    }
+    state.board = board;
 
-        self.ip = ip;
+    Ok((state, interpreter.snapshot()))
+}
 
-        true
-    }
+// Plays the game steering towards the ball like `play` does, but without
+// drawing the board or recording inputs. Picks up from `state`, and returns the
+// state the game ended in, or was in once the joystick has been read
+// `max_frames` times.
+fn solve<M: Machine>(
+    machine: &mut M,
+    mut state: GameState,
+    max_frames: usize,
+) -> Result<GameState, GameError> {
+    loop {
+        match machine.next_output()? {
+            Some(x) => {
+                let y = expect_output(machine)?;
+                let out = expect_output(machine)?;
+                if x == -1 && y == 0 {
+                    state.score = out;
+                    continue;
+                }
 
-    pub fn run(&mut self) {
-        while self.step() {}
+                state.draw(x, y, Tile::from(out));
+            }
+            None if machine.is_halted() => return Ok(state),
+            None if state.frames == max_frames => return Ok(state),
+            None => {
+                let now = Instant::now();
+                let input = joystick_toward(state.paddle, state.ball);
+                state.deciding += now.elapsed();
+                state.frames += 1;
+                machine.provide_input(input);
+            }
+        }
     }
+}
 
-    fn value(&self, parameter: &Parameter) -> i64 {
-        let index = match parameter.mode {
-            ParameterMode::Position => parameter.value as usize,
-            ParameterMode::Relative => (parameter.value + self.relative_base) as usize,
-            ParameterMode::Immediate => {
-                return parameter.value;
-            }
-        };
+// The game with quarters inserted.
+fn free_play(memory: &[i64]) -> Interpreter {
+    let mut interpreter = Interpreter::new(memory);
+    interpreter.load_at(0, &[2]);
+    interpreter.echo_output = false;
+    interpreter
+}
 
-        *self.memory.get(index).unwrap_or(&0)
+fn part2(
+    memory: &[i64],
+    keys: &Receiver<u8>,
+    replay: Option<&[i64]>,
+    frame: Duration,
+) -> Result<(GameState, Vec<i64>), GameError> {
+    if output::ansi() {
+        sayln!("\x1B[3J\x1Bc");
     }
 
-    fn value_mut<'a>(&'a mut self, parameter: &Parameter) -> &'a mut i64 {
-        let index = usize::try_from(match parameter.mode {
-            ParameterMode::Position => parameter.value,
-            ParameterMode::Relative => (parameter.value + self.relative_base),
-            ParameterMode::Immediate => panic!("can't get immediate as mut"),
-        })
-        .unwrap();
+    let (state, snapshot) = read_initial_board(memory)?;
+    let mut machine = free_play(memory);
+    machine.restore(&snapshot);
+    play(&mut machine, state, keys, replay, frame)
+}
 
-        if index >= self.memory.len() {
-            if self.debug {
-                print!("resizing memory to {}\r", index + 1);
-                std::io::stdout().flush().unwrap();
+fn main() -> Result<(), GameError> {
+    let mut filename = None;
+    let mut record = None;
+    let mut replay = None;
+    let mut frame = Duration::default();
+    let mut bench = false;
+
+    // `--fps N` slows the game down to N frames per second.
+    let mut args = output::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--record" => record = Some(args.next().expect("--record needs a file")),
+            "--replay" => replay = Some(args.next().expect("--replay needs a file")),
+            "--fps" => {
+                let fps: u32 = args.next().expect("--fps needs a rate").parse().unwrap();
+                if fps > 0 {
+                    frame = Duration::from_secs(1) / fps;
+                }
             }
-            self.memory.resize(index + 1, 0);
+            "--bench" => bench = true,
+            _ => filename = Some(arg),
         }
-
-        self.memory.get_mut(index).unwrap()
     }
-}
 
-fn part1(memory: &Vec<i64>) -> usize {
-    let mut interpreter = Interpreter::new(memory);
-    let (tx_input, rx_input) = channel();
-    let (tx_output, rx_output) = channel();
-    interpreter.rx = Some(rx_input);
-    interpreter.tx = Some(tx_output);
+    let memory = input::read_program(filename)?;
+    let program = program_hash(&memory);
+
+    // Times the game with and without rendering, which should end the same.
+    if bench {
+        // Nobody's pressing keys during the benchmark.
+        let now = Instant::now();
+        let (played, _) = part2(&memory, &channel().1, None, Duration::default())?;
+        let played = played.score;
+        let rendered = now.elapsed();
+
+        let now = Instant::now();
+        let (state, snapshot) = read_initial_board(&memory)?;
+        let blocks = state.blocks;
+        let mut machine = free_play(&memory);
+        machine.restore(&snapshot);
+        let state = solve(&mut machine, state, usize::MAX)?;
+        let solved = state.score;
+        let fast = now.elapsed();
+
+        if played != solved {
+            panic!("solver scored {}, but the game scored {}", solved, played);
+        }
+        answer(2, solved);
+        sayln!("blocks at the start: {}", blocks);
+        state.report_frames();
+        println!("play: {:?}, solve: {:?}", rendered, fast);
+        return Ok(());
+    }
 
-    let mut map = vec![vec![Tile::Empty; 50]; 26];
-    let mut score = 0;
-    let mut x = None;
-    let mut y = None;
-
-    tx_input.send(1).unwrap();
-    while interpreter.step() {
-        if let Ok(out) = rx_output.try_recv() {
-            if x.is_none() {
-                x = Some(out);
-            } else if y.is_none() {
-                y = Some(out);
-            } else if x == Some(-1) && y == Some(0) {
-                score = out;
-            } else {
-                map[y.unwrap() as usize][x.unwrap() as usize] = Tile::from(out);
-                x = None;
-                y = None;
+    // The terminal is line buffered, so keys only come through after enter.
+    let (tx_keys, keys) = channel();
+    thread::spawn(move || {
+        for byte in std::io::stdin().lock().bytes() {
+            if tx_keys.send(byte.unwrap()).is_err() {
+                break;
             }
         }
+    });
+
+    if let Some(path) = replay {
+        let mut text = String::new();
+        input::open(Some(path))?.read_to_string(&mut text).unwrap();
+        let replay = Replay::parse(&text);
+        if replay.program != program {
+            panic!("replay was recorded on a different program");
+        }
+
+        let (state, _) = part2(&memory, &keys, Some(&replay.inputs), frame)?;
+        let score = state.score;
+        if score != replay.score {
+            panic!("replay scored {}, but {} was recorded", score, replay.score);
+        }
+        answer(2, score);
+        return Ok(());
     }
 
-    draw_map(&map, score);
+    let (part1, (width, height)) = part1(&memory)?;
+    let (state, inputs) = part2(&memory, &keys, None, frame)?;
+    answer(1, part1);
+    sayln!("board: {}x{}", width, height);
+    answer(2, state.score);
+    state.report_frames();
+
+    if let Some(path) = record {
+        let replay = Replay {
+            program,
+            score: state.score,
+            inputs,
+        };
+        std::fs::write(path, replay.to_string()).unwrap();
+    }
 
-    map.iter()
-        .map(|row| row.iter().filter(|tile| **tile == Tile::Block).count())
-        .sum()
+    Ok(())
 }
 
-fn draw_map(map: &Vec<Vec<Tile>>, score: i64) {
-    print!("\x1B[1;1H");
-    for row in map {
-        for tile in row {
-            print!(
-                "{}",
-                match tile {
-                    Tile::Empty => "  ",
-                    Tile::Ball => "⬤ ",
-                    Tile::Block => "█▉",
-                    Tile::HorizontalPaddle => "▀▀",
-                    Tile::Wall => "██",
-                }
-            )
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aoc2019::intcode::machine::{ScriptedMachine, StepResult};
+    use aoc2019::intcode::IntcodeProgram;
+
+    fn real_input() -> Vec<i64> {
+        include_str!("input")
+            .parse::<IntcodeProgram>()
+            .unwrap()
+            .into()
+    }
+
+    // A paddle at x=1 and a ball at x=3, drawn after a score of `score`.
+    fn frame(score: i64) -> Vec<StepResult> {
+        [-1, 0, score, 1, 20, 3, 3, 10, 4]
+            .iter()
+            .map(|&value| StepResult::Output(value))
+            .chain(Some(StepResult::NeedInput))
+            .collect()
+    }
+
+    fn keys(pressed: &[u8]) -> Receiver<u8> {
+        let (tx, rx) = channel();
+        for key in pressed {
+            tx.send(*key).unwrap();
         }
-        println!();
+        rx
     }
-    println!("score: {}", score);
-}
 
-fn part2(memory: &Vec<i64>) -> i64 {
-    println!("\x1B[3J\x1Bc");
-    let mut memory = memory.clone();
-    memory[0] = 2;
+    #[test]
+    fn pause_and_resume() {
+        let mut machine = ScriptedMachine::new([frame(10), frame(20)].concat());
+        let result = play(
+            &mut machine,
+            GameState::default(),
+            &keys(b"  "),
+            None,
+            Duration::default(),
+        );
+        let (state, inputs) = result.unwrap();
+        assert_eq!((state.score, inputs), (20, vec![1, 1]));
+        assert_eq!(machine.inputs, vec![1, 1]);
+    }
 
-    let mut interpreter = Interpreter::new(&memory);
-    let (tx_input, rx_input) = channel();
-    let (tx_output, rx_output) = channel();
-    interpreter.rx = Some(rx_input);
-    interpreter.tx = Some(tx_output);
+    #[test]
+    fn quit_early() {
+        let mut machine = ScriptedMachine::new([frame(10), frame(20)].concat());
+        let result = play(
+            &mut machine,
+            GameState::default(),
+            &keys(b" q"),
+            None,
+            Duration::default(),
+        );
+        let (state, inputs) = result.unwrap();
+        assert_eq!((state.score, inputs), (10, vec![]));
+        assert_eq!(state.frames, 0);
+        assert!(machine.inputs.is_empty());
+    }
 
-    let mut map = vec![vec![Tile::Empty; 50]; 26];
-    let mut paddle = 0;
-    let mut ball = None;
-    let mut score = 0;
-    let mut x = None;
-    let mut y = None;
-
-    tx_input.send(1).unwrap();
-    while interpreter.step() {
-        if let Ok(out) = rx_output.try_recv() {
-            if x.is_none() {
-                x = Some(out);
-            } else if y.is_none() {
-                y = Some(out);
-            } else if x == Some(-1) && y == Some(0) {
-                score = out;
-                x = None;
-                y = None;
-            } else {
-                let x_ = x.unwrap() as usize;
-                let y_ = y.unwrap() as usize;
-                let tile = Tile::from(out);
-                if tile == Tile::HorizontalPaddle {
-                    paddle = x_;
-                } else if tile == Tile::Ball {
-                    ball = Some(x_);
-                }
+    #[test]
+    fn play_counts_frames() {
+        let script = [frame(10), frame(20), frame(30), frame(40)].concat();
+        let mut machine = ScriptedMachine::new(script);
+        let (state, inputs) = play(
+            &mut machine,
+            GameState::default(),
+            &keys(b""),
+            None,
+            Duration::default(),
+        )
+        .unwrap();
+        assert_eq!(state.frames, 4);
+        assert_eq!(inputs.len(), state.frames);
+        assert_eq!(state.score, 40);
+    }
 
-                map[y_][x_] = tile;
+    #[test]
+    fn render_tiles() {
+        let grid = vec![
+            vec![Tile::Wall, Tile::Block, Tile::Wall],
+            vec![Tile::Empty, Tile::Ball, Tile::HorizontalPaddle],
+        ];
+        assert_eq!(render_grid(&grid), "███▉██\n  ⬤ ▀▀\n");
+    }
 
-                x = None;
-                y = None;
-            }
+    #[test]
+    fn replay_round_trip() {
+        let script = [frame(10), frame(20), frame(30)].concat();
+        let (state, inputs) = play(
+            &mut ScriptedMachine::new(script.clone()),
+            GameState::default(),
+            &keys(b""),
+            None,
+            Duration::default(),
+        )
+        .unwrap();
 
-            if let Some(ball_) = ball {
-                draw_map(&map, score);
+        let replay = Replay {
+            program: program_hash(&[1, 2, 3]),
+            score: state.score,
+            inputs,
+        };
+        let parsed = Replay::parse(&replay.to_string());
+        assert_eq!(parsed.program, replay.program);
+        assert_eq!(parsed.score, 30);
+        assert_eq!(parsed.inputs, replay.inputs);
+
+        let mut machine = ScriptedMachine::new(script);
+        let result = play(
+            &mut machine,
+            GameState::default(),
+            &keys(b""),
+            Some(&parsed.inputs),
+            Duration::default(),
+        );
+        assert_eq!(result.unwrap().0.score, parsed.score);
+        assert_eq!(machine.inputs, parsed.inputs);
+    }
 
-                if paddle > ball_ {
-                    tx_input.send(-1).unwrap();
-                } else if paddle == ball_ {
-                    tx_input.send(0).unwrap();
-                } else if paddle < ball_ {
-                    tx_input.send(1).unwrap();
-                }
+    #[test]
+    fn solver_steers_scripted_game() {
+        // The paddle stays at x=5 while the ball moves left, right and then
+        // lines up with it.
+        let script: Vec<StepResult> = [2, 8, 5]
+            .iter()
+            .flat_map(|&ball| {
+                vec![
+                    StepResult::Output(5),
+                    StepResult::Output(20),
+                    StepResult::Output(3),
+                    StepResult::Output(ball),
+                    StepResult::Output(10),
+                    StepResult::Output(4),
+                    StepResult::NeedInput,
+                ]
+            })
+            .collect();
+        let mut machine = ScriptedMachine::new(script);
+        let state = solve(&mut machine, GameState::default(), usize::MAX).unwrap();
+        assert_eq!(machine.inputs, vec![-1, 1, 0]);
+        assert_eq!(state.frames, 3);
+    }
 
-                ball = None;
-            }
-        }
+    #[test]
+    fn solver_matches_play() {
+        let script = [frame(10), frame(20), frame(30)].concat();
+        let (played, inputs) = play(
+            &mut ScriptedMachine::new(script.clone()),
+            GameState::default(),
+            &keys(b""),
+            None,
+            Duration::default(),
+        )
+        .unwrap();
+
+        let mut machine = ScriptedMachine::new(script);
+        let state = solve(&mut machine, GameState::default(), usize::MAX).unwrap();
+        assert_eq!(state.score, played.score);
+        assert_eq!(state.frames, played.frames);
+        assert_eq!(machine.inputs, inputs);
     }
 
-    score
-}
+    #[test]
+    fn joystick_follows_ball() {
+        assert_eq!(joystick_toward(5, 2), -1);
+        assert_eq!(joystick_toward(2, 5), 1);
+        assert_eq!(joystick_toward(3, 3), 0);
+    }
+
+    #[test]
+    fn real_board_dimensions() {
+        let (_, dimensions) = part1(&real_input()).unwrap();
+        assert_eq!(dimensions, (46, 26));
+    }
 
-fn main() {
-    let file = File::open(std::env::args().nth(1).expect("no filename provided")).unwrap();
-    let mut input = String::new();
-    BufReader::new(file).read_line(&mut input).unwrap();
+    #[test]
+    fn initial_board() {
+        let (state, _) = read_initial_board(&real_input()).unwrap();
+        assert_eq!(state.blocks, 324);
+        assert_eq!(state.score, 0);
+        assert_eq!(state.board.len(), 26);
+    }
 
-    let memory: Vec<i64> = input
-        .split(",")
-        .map(|x| x.trim().parse().unwrap())
-        .collect();
+    #[test]
+    fn unthrottled_game() {
+        let now = Instant::now();
+        let (state, _) = part2(&real_input(), &keys(b""), None, Duration::default()).unwrap();
+        assert_eq!(state.score, 15957);
+        assert!(now.elapsed() < Duration::from_secs(30));
+    }
 
-    let part1 = part1(&memory);
-    let part2 = part2(&memory);
-    dbg!(part1, part2);
+    #[test]
+    fn solver_clears_every_block() {
+        let memory = real_input();
+        let (state, snapshot) = read_initial_board(&memory).unwrap();
+        let mut machine = free_play(&memory);
+        machine.restore(&snapshot);
+
+        // The real game takes about 5000 frames.
+        let state = solve(&mut machine, state, 10_000).unwrap();
+        assert!(machine.is_halted());
+        assert_eq!(state.blocks, 0);
+        assert_eq!(state.score, 15957);
+    }
 }
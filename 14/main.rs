@@ -1,6 +1,9 @@
-use std::cmp::Ordering;
+use aoc2019::input::{self, InputError};
+use aoc2019::output::{self, answer};
+use aoc2019::{say, sayln};
+use std::cell::Cell;
 use std::collections::HashMap;
-use std::fmt::{Debug, Display, Formatter};
+use std::fmt::{Debug, Formatter};
 use std::io::BufRead;
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
@@ -34,7 +37,7 @@ impl Reaction {
             .next()
             .unwrap()
             .split(", ")
-            .map(|component| Component::from_str(component))
+            .map(Component::from_str)
             .collect();
         let result = Component::from_str(sides.next().unwrap());
 
@@ -56,7 +59,7 @@ impl Debug for Component {
             .map(|x| {
                 "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ"
                     .chars()
-                    .nth(*x as usize)
+                    .nth(*x)
                     .unwrap()
                     .to_string()
             })
@@ -67,11 +70,8 @@ impl Debug for Component {
     }
 }
 
-fn read_input() -> HashMap<usize, Reaction> {
-    let stdin = std::io::stdin();
-
-    stdin
-        .lock()
+fn parse_reactions(reader: impl BufRead) -> HashMap<usize, Reaction> {
+    reader
         .lines()
         .map(|line| {
             let reaction = Reaction::from_str(&line.unwrap());
@@ -80,62 +80,352 @@ fn read_input() -> HashMap<usize, Reaction> {
         .collect()
 }
 
-fn part1(reactions: &HashMap<usize, Reaction>) -> usize {
-    let ore = usize::from_str_radix("ORE", 36).unwrap();
-    let mut required_ore = 0;
-    let mut produced = 0;
-
-    let mut buffer: HashMap<usize, usize> = HashMap::new();
-    buffer.insert(ore, 1_000_000_000_000);
-    let mut required = vec![Component::from_str("1 FUEL")];
-    loop {
-        if buffer.get(&ore).unwrap() % 1_000_000 == 0 {
-        println!("{:?}", buffer.get(&ore));
+// One `7 A, 1 E => 1 FUEL` line per reaction, sorted.
+fn format_reactions(reactions: &HashMap<usize, Reaction>) -> String {
+    let mut lines: Vec<_> = reactions
+        .values()
+        .map(|reaction| {
+            let components: Vec<_> = reaction
+                .components
+                .iter()
+                .map(|component| format!("{:?}", component))
+                .collect();
+            format!("{} => {:?}\n", components.join(", "), reaction.result)
+        })
+        .collect();
+    lines.sort();
+    lines.concat()
+}
+
+// The raw materials one unit of a component takes, and how many reactions.
+type UnitCost = (HashMap<usize, usize>, usize);
+
+// Components made one at a time out of raw materials, or out of other such
+// components, never leave a surplus, so each unit always costs the same. Keeps
+// those per-unit costs.
+struct LinearCosts {
+    costs: HashMap<usize, UnitCost>,
+    hits: Cell<usize>,
+}
+
+impl LinearCosts {
+    fn new(reactions: &HashMap<usize, Reaction>) -> Self {
+        let mut costs = HashMap::new();
+        for name in reactions.keys() {
+            Self::unit_cost(reactions, *name, &mut costs);
+        }
+
+        // Only the components that turned out to have a constant cost.
+        let costs = costs
+            .into_iter()
+            .filter_map(|(name, cost)| Some((name, cost?)))
+            .collect();
+        Self {
+            costs,
+            hits: Cell::new(0),
         }
-        if required.is_empty() {
-            produced += 1;
-            required.push(Component::from_str("1 FUEL"));
+    }
+
+    // None if making `name` can leave a surplus somewhere along the way.
+    fn unit_cost(
+        reactions: &HashMap<usize, Reaction>,
+        name: usize,
+        costs: &mut HashMap<usize, Option<UnitCost>>,
+    ) -> Option<UnitCost> {
+        if let Some(cost) = costs.get(&name) {
+            return cost.clone();
         }
 
-        let mut component = required.pop().unwrap();
+        let reaction = match reactions.get(&name) {
+            Some(reaction) => reaction,
+            None => return Some((vec![(name, 1)].into_iter().collect(), 0)),
+        };
 
-        // dbg!(component);
-        if let Some(buffered) = buffer.remove(&component.name) {
-            // dbg!(buffered);
-            component.count = match buffered.cmp(&component.count) {
-                Ordering::Greater => {
-                    buffer.insert(component.name, buffered - component.count);
-                    0
+        let mut cost = None;
+        if reaction.result.count == 1 {
+            let mut raw = HashMap::new();
+            let mut applied = 1;
+            let linear = reaction.components.iter().all(|component| {
+                match Self::unit_cost(reactions, component.name, costs) {
+                    Some((unit, unit_applied)) => {
+                        for (material, count) in unit {
+                            *raw.entry(material).or_insert(0) += count * component.count;
+                        }
+                        applied += unit_applied * component.count;
+                        true
+                    }
+                    None => false,
                 }
-                Ordering::Equal => 0,
-                Ordering::Less => component.count - buffered,
+            });
+            if linear {
+                cost = Some((raw, applied));
             }
         }
 
-        if component.count == 0 {
+        costs.insert(name, cost.clone());
+        cost
+    }
+
+    fn get(&self, name: usize) -> Option<&UnitCost> {
+        let cost = self.costs.get(&name);
+        if cost.is_some() {
+            self.hits.set(self.hits.get() + 1);
+        }
+        cost
+    }
+}
+
+// Expands `fuel` FUEL down to raw materials (anything no reaction produces).
+// Returns the total amount of every raw material and how many reactions were
+// applied along the way.
+fn expand(reactions: &HashMap<usize, Reaction>, fuel: usize) -> (HashMap<usize, usize>, usize) {
+    expand_cached(reactions, fuel, None)
+}
+
+// Like expand, but takes components with a known per-unit cost from `linear`
+// instead of expanding them again.
+fn expand_cached(
+    reactions: &HashMap<usize, Reaction>,
+    fuel: usize,
+    linear: Option<&LinearCosts>,
+) -> (HashMap<usize, usize>, usize) {
+    let mut costs = HashMap::new();
+    let mut applied = 0;
+    let mut surplus: HashMap<usize, usize> = HashMap::new();
+    let mut required = vec![Component {
+        count: fuel,
+        name: usize::from_str_radix("FUEL", 36).unwrap(),
+    }];
+
+    while let Some(mut component) = required.pop() {
+        if let Some((unit, unit_applied)) = linear.and_then(|linear| linear.get(component.name)) {
+            for (material, count) in unit {
+                *costs.entry(*material).or_insert(0) += count * component.count;
+            }
+            applied += unit_applied * component.count;
             continue;
-        } else if component.name == ore {
-            break;
         }
 
-        let reaction = reactions.get(&component.name).unwrap();
-        let count = (component.count as f64 / reaction.result.count as f64).ceil() as usize;
-        for _ in 0..count {
-            for requirement in &reaction.components {
-                required.push(*requirement);
+        let reaction = match reactions.get(&component.name) {
+            Some(reaction) => reaction,
+            None => {
+                *costs.entry(component.name).or_insert(0) += component.count;
+                continue;
             }
+        };
+
+        let buffered = surplus.remove(&component.name).unwrap_or(0);
+        if buffered >= component.count {
+            surplus.insert(component.name, buffered - component.count);
+            continue;
+        }
+        component.count -= buffered;
+
+        let batches = component.count.div_ceil(reaction.result.count);
+        applied += batches;
+        for requirement in &reaction.components {
+            required.push(Component {
+                count: requirement.count * batches,
+                name: requirement.name,
+            });
         }
 
-        buffer.insert(
-            reaction.result.name,
-            reaction.result.count * count - component.count,
+        surplus.insert(
+            component.name,
+            reaction.result.count * batches - component.count,
         );
     }
 
-    produced
+    (costs, applied)
+}
+
+fn ore_for_fuel(reactions: &HashMap<usize, Reaction>, fuel: usize) -> HashMap<usize, usize> {
+    expand(reactions, fuel).0
 }
 
-fn main() {
-    let reactions = read_input();
-    dbg!(part1(&reactions));
+// Average ore per fuel when making `fuel` at once. Surplus from one fuel's
+// reactions gets used for the next, so this drops as `fuel` grows, which is why
+// part 2 can't just divide by part 1.
+fn ore_per_fuel(reactions: &HashMap<usize, Reaction>, fuel: usize) -> f64 {
+    let ore = usize::from_str_radix("ORE", 36).unwrap();
+    ore_for_fuel(reactions, fuel)[&ore] as f64 / fuel as f64
+}
+
+fn reaction_count(reactions: &HashMap<usize, Reaction>, fuel: usize) -> usize {
+    expand(reactions, fuel).1
+}
+
+fn part1(reactions: &HashMap<usize, Reaction>) -> usize {
+    let ore = usize::from_str_radix("ORE", 36).unwrap();
+    ore_for_fuel(reactions, 1)[&ore]
+}
+
+// The most fuel `budget` ore can make, and how much ore is left over.
+fn max_fuel(reactions: &HashMap<usize, Reaction>, budget: usize) -> (usize, usize) {
+    let linear = LinearCosts::new(reactions);
+    let result = search_fuel(reactions, budget, &linear);
+    sayln!("linear cost hits: {}", linear.hits.get());
+    result
+}
+
+// Binary searches for max_fuel, taking per-unit costs from `linear`.
+fn search_fuel(
+    reactions: &HashMap<usize, Reaction>,
+    budget: usize,
+    linear: &LinearCosts,
+) -> (usize, usize) {
+    let ore = usize::from_str_radix("ORE", 36).unwrap();
+    let ore_for_fuel = |fuel| expand_cached(reactions, fuel, Some(linear)).0;
+
+    let mut low = 0;
+    let mut high = budget;
+    while low < high {
+        let fuel = (low + high).div_ceil(2);
+        if ore_for_fuel(fuel).get(&ore).copied().unwrap_or(0) <= budget {
+            low = fuel;
+        } else {
+            high = fuel - 1;
+        }
+    }
+
+    let used = ore_for_fuel(low).get(&ore).copied().unwrap_or(0);
+    (low, budget - used)
+}
+
+fn part2(reactions: &HashMap<usize, Reaction>) -> usize {
+    let (fuel, left) = max_fuel(reactions, 1_000_000_000_000);
+    sayln!("ore left: {}", left);
+    fuel
+}
+
+fn main() -> Result<(), InputError> {
+    let mut filename = None;
+    let mut verbose = false;
+    for arg in output::args().skip(1) {
+        match arg.as_str() {
+            "--verbose" => verbose = true,
+            _ => filename = Some(arg),
+        }
+    }
+
+    let reactions = parse_reactions(input::reader(filename)?);
+    if verbose {
+        say!("{}", format_reactions(&reactions));
+    }
+    answer(1, part1(&reactions));
+    sayln!("reactions: {}", reaction_count(&reactions, 1));
+    sayln!(
+        "ore per fuel: {} for one, {:.2} in bulk",
+        ore_per_fuel(&reactions, 1),
+        ore_per_fuel(&reactions, 1_000_000)
+    );
+    answer(2, part2(&reactions));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "10 ORE => 10 A
+1 ORE => 1 B
+7 A, 1 B => 1 C
+7 A, 1 C => 1 D
+7 A, 1 D => 1 E
+7 A, 1 E => 1 FUEL
+";
+
+    fn name(name: &str) -> usize {
+        usize::from_str_radix(name, 36).unwrap()
+    }
+
+    #[test]
+    fn two_raw_materials() {
+        let reactions = parse_reactions("2 ORE, 3 SAND => 1 A\n1 A, 5 SAND => 1 FUEL\n".as_bytes());
+        let costs = ore_for_fuel(&reactions, 1);
+        assert_eq!(costs.len(), 2);
+        assert_eq!(costs[&name("ORE")], 2);
+        assert_eq!(costs[&name("SAND")], 8);
+    }
+
+    #[test]
+    fn format_round_trip() {
+        let reactions = parse_reactions(SAMPLE.as_bytes());
+        let mut lines: Vec<_> = SAMPLE.lines().collect();
+        lines.sort();
+        assert_eq!(format_reactions(&reactions), lines.join("\n") + "\n");
+    }
+
+    #[test]
+    fn reactions_for_one_fuel() {
+        let reactions = parse_reactions(SAMPLE.as_bytes());
+        assert_eq!(part1(&reactions), 31);
+        // Three batches of A, and one of everything else.
+        assert_eq!(reaction_count(&reactions, 1), 8);
+    }
+
+    #[test]
+    fn max_fuel_and_leftover() {
+        let reactions = parse_reactions(include_str!("example3").as_bytes());
+        let budget = 1_000_000_000_000;
+        let (fuel, left) = max_fuel(&reactions, budget);
+        assert_eq!(fuel, 82892753);
+
+        // What's left is what the fuel didn't use, and isn't enough for more.
+        let ore = &name("ORE");
+        assert_eq!(left, budget - ore_for_fuel(&reactions, fuel)[ore]);
+        assert!(ore_for_fuel(&reactions, fuel + 1)[ore] > budget);
+        assert!(left < 13312);
+    }
+
+    #[test]
+    fn bulk_is_cheaper_per_unit() {
+        let reactions = parse_reactions(include_str!("example3").as_bytes());
+        let ore = &name("ORE");
+        let unit = ore_for_fuel(&reactions, 1)[ore];
+        let bulk = ore_for_fuel(&reactions, 1_000_000)[ore];
+        assert_eq!(unit, 13312);
+        assert!(bulk < unit * 1_000_000);
+
+        // So dividing the budget by the cost of one fuel comes up short.
+        let budget = 1_000_000_000_000;
+        assert_eq!(budget / unit, 75120192);
+        assert_eq!(max_fuel(&reactions, budget).0, 82892753);
+    }
+
+    #[test]
+    fn linear_costs_match_expansion() {
+        let examples = [
+            SAMPLE,
+            include_str!("example2"),
+            include_str!("example3"),
+            include_str!("example4"),
+            include_str!("example5"),
+        ];
+        for example in &examples {
+            let reactions = parse_reactions(example.as_bytes());
+            let linear = LinearCosts::new(&reactions);
+            for fuel in &[1, 7, 1000] {
+                assert_eq!(
+                    expand_cached(&reactions, *fuel, Some(&linear)),
+                    expand(&reactions, *fuel)
+                );
+            }
+        }
+
+        // B costs one ore a unit, and the search keeps needing it.
+        let reactions = parse_reactions(SAMPLE.as_bytes());
+        let linear = LinearCosts::new(&reactions);
+        assert_eq!(
+            linear.costs[&name("B")],
+            (vec![(name("ORE"), 1)].into_iter().collect(), 1)
+        );
+        let (fuel, left) = search_fuel(&reactions, 1000, &linear);
+        assert!(linear.hits.get() > 0);
+
+        let ore = &name("ORE");
+        assert_eq!(ore_for_fuel(&reactions, fuel)[ore] + left, 1000);
+        assert!(ore_for_fuel(&reactions, fuel + 1)[ore] > 1000);
+    }
 }
@@ -1,10 +1,13 @@
-use std::collections::{HashSet, VecDeque};
-use std::convert::{From, TryFrom};
-use std::fmt::{Display, Formatter};
-use std::fs::File;
-use std::io::{BufRead, BufReader, Write};
-use std::sync::mpsc::{channel, Receiver, Sender};
-use text_io::{try_read, try_scan};
+use aoc2019::input::{self, InputError};
+use aoc2019::intcode::interpreter::{IntcodeError, Interpreter};
+use aoc2019::intcode::machine::Machine;
+use aoc2019::map::{render_grid, Renderable};
+use aoc2019::output::{self, answer};
+use aoc2019::sayln;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::From;
+use std::fmt::{Debug, Display, Formatter};
+use std::io::Write;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Movement {
@@ -13,9 +16,9 @@ enum Movement {
     West,
     East,
 }
-impl Into<i64> for &Movement {
-    fn into(self) -> i64 {
-        match self {
+impl From<&Movement> for i64 {
+    fn from(movement: &Movement) -> Self {
+        match movement {
             Movement::North => 1,
             Movement::South => 2,
             Movement::West => 3,
@@ -35,8 +38,8 @@ impl Movement {
 
     fn coords(&self) -> (i64, i64) {
         match self {
-            Movement::North => (0, 1),
-            Movement::South => (0, -1),
+            Movement::North => (0, -1),
+            Movement::South => (0, 1),
             Movement::West => (-1, 0),
             Movement::East => (1, 0),
         }
@@ -60,292 +63,160 @@ impl From<i64> for Status {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum Opcode {
-    Add,
-    Multiply,
-    Read,
-    Write,
-    JumpIfTrue,
-    JumpIfFalse,
-    LessThan,
-    Equals,
-    RelativeBase,
-    Halt,
+struct Maze {
+    map: Vec<Vec<bool>>,
+    open: HashSet<(i64, i64)>,
+    oxygen: (i64, i64),
 }
 
-impl From<i64> for Opcode {
-    fn from(item: i64) -> Self {
-        match item {
-            1 => Opcode::Add,
-            2 => Opcode::Multiply,
-            3 => Opcode::Read,
-            4 => Opcode::Write,
-            5 => Opcode::JumpIfTrue,
-            6 => Opcode::JumpIfFalse,
-            7 => Opcode::LessThan,
-            8 => Opcode::Equals,
-            9 => Opcode::RelativeBase,
-            99 => Opcode::Halt,
-            _ => panic!("unknown instruction {}", item),
-        }
+impl Maze {
+    fn open_cells(&self) -> HashSet<(i64, i64)> {
+        self.open.clone()
     }
-}
 
-#[derive(Debug, PartialEq, Eq)]
-enum ParameterMode {
-    Position,
-    Immediate,
-    Relative,
-}
-
-impl From<i64> for ParameterMode {
-    fn from(item: i64) -> Self {
-        match item {
-            0 => ParameterMode::Position,
-            1 => ParameterMode::Immediate,
-            2 => ParameterMode::Relative,
-            _ => panic!("unknown parameter mode {}", item),
-        }
+    fn oxygen(&self) -> (i64, i64) {
+        self.oxygen
     }
-}
 
-#[derive(Debug)]
-struct Parameter {
-    pub mode: ParameterMode,
-    pub value: i64,
-}
+    // Breadth-first distance from the start to every open cell.
+    fn distances(&self) -> HashMap<(i64, i64), usize> {
+        let mut distances = HashMap::new();
+        let mut queue = VecDeque::from(vec![((0, 0), 0)]);
+        while let Some((cell, distance)) = queue.pop_front() {
+            if !self.open.contains(&cell) || distances.contains_key(&cell) {
+                continue;
+            }
+            distances.insert(cell, distance);
 
-impl Parameter {
-    fn new(mode: ParameterMode, value: i64) -> Self {
-        Self { mode, value }
+            let (x, y) = cell;
+            queue.push_back(((x, y - 1), distance + 1));
+            queue.push_back(((x, y + 1), distance + 1));
+            queue.push_back(((x - 1, y), distance + 1));
+            queue.push_back(((x + 1, y), distance + 1));
+        }
+        distances
     }
-}
 
-impl Display for Parameter {
-    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
-        match self.mode {
-            ParameterMode::Immediate => write!(formatter, "{}", self.value),
-            ParameterMode::Position => write!(formatter, "[{}]", self.value),
-            ParameterMode::Relative => write!(formatter, "rel[{}]", self.value),
+    // Draws the maze with every open cell labeled with the last two digits of
+    // its distance from the start.
+    fn render_distances(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        let distances = self.distances();
+        for (y, row) in self.map.iter().enumerate() {
+            for (x, wall) in row.iter().enumerate() {
+                let cell = (x as i64 - 25, y as i64 - 25);
+                match distances.get(&cell) {
+                    Some(distance) => write!(writer, "{:>2}", distance % 100)?,
+                    None if *wall => write!(writer, "██")?,
+                    None => write!(writer, "  ")?,
+                }
+            }
+            writeln!(writer)?;
         }
+        Ok(())
     }
 }
 
-#[derive(Debug)]
-struct Instruction {
-    pub opcode: Opcode,
-    pub parameters: (Parameter, Parameter, Parameter),
+// What exploring the maze turned up. Coordinates are relative to where the droid
+// started.
+struct MazeResult {
+    shortest_distance: usize,
+    maze: Maze,
+    oxygen: (i64, i64),
+    start: (i64, i64),
 }
 
-impl Instruction {
-    pub fn fetch(ip: i64, memory: &Vec<i64>) -> Option<Self> {
-        let ip = ip as usize;
-        let instruction = memory.get(ip)?;
-
-        let opcode = Opcode::from(instruction % 100);
-        let parameters = (
-            Parameter::new(
-                ParameterMode::from(instruction / 100 % 10),
-                *memory.get(ip + 1).unwrap_or(&0),
-            ),
-            Parameter::new(
-                ParameterMode::from(instruction / 1000 % 10),
-                *memory.get(ip + 2).unwrap_or(&0),
-            ),
-            Parameter::new(
-                ParameterMode::from(instruction / 10000 % 10),
-                *memory.get(ip + 3).unwrap_or(&0),
-            ),
-        );
-
-        Some(Self { opcode, parameters })
+impl MazeResult {
+    fn new(shortest_distance: usize, maze: Maze) -> Self {
+        Self {
+            shortest_distance,
+            oxygen: maze.oxygen(),
+            maze,
+            start: (0, 0),
+        }
     }
 }
 
-struct Interpreter {
-    pub memory: Vec<i64>,
-    pub rx: Option<Receiver<i64>>,
-    pub tx: Option<Sender<i64>>,
-    pub last_output: Option<i64>,
-    pub ip: i64,
-    pub relative_base: i64,
-    pub debug: bool,
+enum DroidError {
+    Input(InputError),
+    MovedIntoWall((i64, i64)),
+    HitWallInOpenCell((i64, i64)),
+    OxygenMismatch((i64, i64)),
+    // The droid stopped answering, either because its program halted or
+    // because it wants input nobody has queued.
+    Halted,
+    NeedsInput,
+    Intcode(IntcodeError),
 }
 
-impl Interpreter {
-    fn new(memory: &Vec<i64>) -> Self {
-        Self {
-            memory: memory.clone(),
-            rx: None,
-            tx: None,
-            last_output: None,
-            ip: 0,
-            relative_base: 0,
-            debug: false,
-        }
+impl From<InputError> for DroidError {
+    fn from(error: InputError) -> Self {
+        DroidError::Input(error)
     }
+}
 
-    fn reset(&mut self, memory: &Vec<i64>) {
-        self.memory = memory.clone();
-        self.ip = 0;
-        self.relative_base = 0;
+impl From<IntcodeError> for DroidError {
+    fn from(error: IntcodeError) -> Self {
+        DroidError::Intcode(error)
     }
+}
 
-    pub fn step(&mut self) -> bool {
-        let instruction = Instruction::fetch(self.ip, &&self.memory).unwrap();
-        let (a, b, c) = &instruction.parameters;
-
-        if self.debug {
-            let args = format!("{:?} {}, {}, {}", instruction.opcode, a, b, c);
-            print!(
-                "ip={:<5} rb={:<5} | {:<30} | {:>5} -> ?        ",
-                self.ip,
-                self.relative_base,
-                args,
-                self.memory.len()
-            );
-            std::io::stdout().flush().unwrap();
-        }
-
-        let (ip, arg_count) = match instruction.opcode {
-            Opcode::Add => {
-                *self.value_mut(&c) = self.value(&a) + self.value(&b);
-                (self.ip + 4, 3)
-            }
-
-            Opcode::Multiply => {
-                *self.value_mut(&c) = self.value(&a) * self.value(&b);
-                (self.ip + 4, 3)
-            }
-
-            Opcode::Read => {
-                *self.value_mut(&a) = match &self.rx {
-                    Some(rx) => {
-                        let input = rx.recv().unwrap();
-                        if self.debug {
-                            print!(">> {}", input);
-                        }
-                        input
-                    }
-                    None => {
-                        print!(">> ");
-                        std::io::stdout().flush().unwrap();
-                        try_read!().unwrap()
-                    }
-                };
-                (self.ip + 2, 1)
-            }
-
-            Opcode::Write => {
-                let value = self.value(&a);
-                self.last_output = Some(value);
-                match &self.tx {
-                    Some(tx) => {
-                        let _ = tx.send(value);
-                    }
-                    None => print!("<< {}", value),
-                }
-                (self.ip + 2, 1)
-            }
-
-            Opcode::JumpIfTrue => (
-                if self.value(&a) != 0 {
-                    self.value(&b)
-                } else {
-                    self.ip + 3
-                },
-                2,
-            ),
-
-            Opcode::JumpIfFalse => (
-                if self.value(&a) == 0 {
-                    self.value(&b)
-                } else {
-                    self.ip + 3
-                },
-                2,
-            ),
-
-            Opcode::LessThan => {
-                let result = self.value(&a) < self.value(&b);
-                *self.value_mut(&c) = if result { 1 } else { 0 };
-                (self.ip + 4, 3)
-            }
-
-            Opcode::Equals => {
-                let result = self.value(&a) == self.value(&b);
-                *self.value_mut(&c) = if result { 1 } else { 0 };
-                (self.ip + 4, 3)
-            }
-
-            Opcode::RelativeBase => {
-                self.relative_base += self.value(&a);
-                (self.ip + 2, 1)
+impl Display for DroidError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            DroidError::Input(error) => write!(formatter, "{}", error),
+            DroidError::MovedIntoWall(cell) => {
+                write!(formatter, "droid moved into the wall at {:?}", cell)
             }
-
-            Opcode::Halt => {
-                return false;
+            DroidError::HitWallInOpenCell(cell) => {
+                write!(formatter, "droid hit a wall in open cell {:?}", cell)
             }
-        };
-
-        if self.debug {
-            let args = match arg_count {
-                1 => format!("{:?} {}", instruction.opcode, a),
-                2 => format!("{:?} {}, {}", instruction.opcode, a, b),
-                3 => format!("{:?} {}, {}, {}", instruction.opcode, a, b, c),
-                _ => panic!(),
-            };
-
-            if instruction.opcode == Opcode::Read {
-                print!("\x1B[1A");
+            DroidError::OxygenMismatch(cell) => {
+                write!(
+                    formatter,
+                    "droid disagrees about the oxygen system at {:?}",
+                    cell
+                )
             }
-
-            println!(
-                "\rip=\x1B[5C rb=\x1B[5C | {:<30} | \x1B[5C -> {:?}",
-                args,
-                self.memory.len()
-            );
+            DroidError::Halted => write!(formatter, "droid program halted"),
+            DroidError::NeedsInput => write!(formatter, "droid wants input but none is queued"),
+            DroidError::Intcode(error) => write!(formatter, "{}", error),
         }
-
-        self.ip = ip;
-
-        true
     }
+}
 
-    pub fn run(&mut self) {
-        while self.step() {}
+impl Debug for DroidError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        Display::fmt(self, formatter)
     }
+}
 
-    fn value(&self, parameter: &Parameter) -> i64 {
-        let index = match parameter.mode {
-            ParameterMode::Position => parameter.value as usize,
-            ParameterMode::Relative => (parameter.value + self.relative_base) as usize,
-            ParameterMode::Immediate => {
-                return parameter.value;
-            }
-        };
-
-        *self.memory.get(index).unwrap_or(&0)
+fn stopped<M: Machine>(droid: &M) -> DroidError {
+    if droid.is_halted() {
+        DroidError::Halted
+    } else {
+        DroidError::NeedsInput
     }
+}
 
-    fn value_mut<'a>(&'a mut self, parameter: &Parameter) -> &'a mut i64 {
-        let index = usize::try_from(match parameter.mode {
-            ParameterMode::Position => parameter.value,
-            ParameterMode::Relative => (parameter.value + self.relative_base),
-            ParameterMode::Immediate => panic!("can't get immediate as mut"),
-        })
-        .unwrap();
+enum Cell {
+    Wall,
+    Oxygen,
+    Droid,
+    Start,
+    Open,
+    Unknown,
+}
 
-        if index >= self.memory.len() {
-            if self.debug {
-                print!("resizing memory to {}\r", index + 1);
-                std::io::stdout().flush().unwrap();
-            }
-            self.memory.resize(index + 1, 0);
+impl Renderable for Cell {
+    fn glyph(&self) -> &str {
+        match self {
+            Cell::Wall => "██",
+            Cell::Oxygen => "⛳",
+            Cell::Droid => "🦀",
+            Cell::Start => "🚦",
+            Cell::Open => "  ",
+            Cell::Unknown => "▒▒",
         }
-
-        self.memory.get_mut(index).unwrap()
     }
 }
 
@@ -356,35 +227,46 @@ fn draw_map(
     dy: i64,
     oxygen: Option<(i64, i64)>,
 ) {
-    print!("\x1B[1;1H");
-    for (y, row) in map.iter().enumerate() {
-        for (x, tile) in row.iter().enumerate() {
-            let x = x as i64 - 25;
-            let y = y as i64 - 25;
-            if *tile {
-                print!("██")
-            } else if oxygen == Some((x, y)) {
-                print!("⛳")
-            } else if dx == x && dy == y {
-                print!("🦀")
-            } else if x == 0 && y == 0 {
-                print!("🚦")
-            } else if seen.contains(&(x, y)) {
-                print!("  ")
-            } else {
-                print!("▒▒")
-            }
-        }
-        println!();
+    if output::quiet() {
+        return;
     }
+
+    let grid: Vec<Vec<Cell>> = map
+        .iter()
+        .enumerate()
+        .map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(x, tile)| {
+                    let x = x as i64 - 25;
+                    let y = y as i64 - 25;
+                    if *tile {
+                        Cell::Wall
+                    } else if oxygen == Some((x, y)) {
+                        Cell::Oxygen
+                    } else if dx == x && dy == y {
+                        Cell::Droid
+                    } else if x == 0 && y == 0 {
+                        Cell::Start
+                    } else if seen.contains(&(x, y)) {
+                        Cell::Open
+                    } else {
+                        Cell::Unknown
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    print!("{}{}", output::home(), render_grid(&grid));
 }
 
-fn part1(memory: &Vec<i64>) -> (usize, Vec<Vec<bool>>, (i64, i64)) {
+// Explores breadth-first, resetting the droid and replaying the whole path from
+// the start to reach each new cell. With `animate` on, the map is redrawn every
+// time the droid runs out of moves.
+fn part1(memory: &[i64], animate: bool) -> Result<MazeResult, DroidError> {
     let mut interpreter = Interpreter::new(memory);
-    let (tx_input, rx_input) = channel();
-    let (tx_output, rx_output) = channel();
-    interpreter.rx = Some(rx_input);
-    interpreter.tx = Some(tx_output);
+    interpreter.echo_output = false;
 
     let mut map = vec![vec![false; 50]; 50];
 
@@ -396,78 +278,267 @@ fn part1(memory: &Vec<i64>) -> (usize, Vec<Vec<bool>>, (i64, i64)) {
     ]);
 
     let mut seen = HashSet::new();
+    let mut open = HashSet::new();
+    open.insert((0, 0));
     let mut queue = VecDeque::from(vec![Movement::North]);
     let (mut path, (mut x, mut y)) = stack.pop_front().unwrap();
 
     let mut oxygen = None;
     let mut distance = None;
+    let mut droid = (0, 0);
+
+    let mut movement = queue.pop_front().unwrap();
+    interpreter.provide_input((&movement).into());
+    while let Some(out) = interpreter.next_output()? {
+        let status = Status::from(out);
+
+        // Check the droid's response against what we already know about the
+        // cell it tried to move into, so a misbehaving program can't
+        // silently corrupt the map.
+        let (dx, dy) = movement.coords();
+        let target = (droid.0 + dx, droid.1 + dy);
+        match &status {
+            Status::HitWall => {
+                if open.contains(&target) {
+                    return Err(DroidError::HitWallInOpenCell(target));
+                }
+                map[(target.1 + 25) as usize][(target.0 + 25) as usize] = true;
+            }
+            Status::Moved | Status::Found => {
+                if map[(target.1 + 25) as usize][(target.0 + 25) as usize] {
+                    return Err(DroidError::MovedIntoWall(target));
+                }
 
-    let movement = &queue.pop_front().unwrap();
-    tx_input.send(movement.into()).unwrap();
-    while interpreter.step() {
-        if let Ok(out) = rx_output.try_recv() {
-            let status = Status::from(out);
-            if queue.is_empty() {
-                // draw_map(&map, &seen, x, y, oxygen);
-                match &status {
-                    Status::HitWall => {
-                        let y_ = (y + 25) as usize;
-                        let x_ = (x + 25) as usize;
-                        map[y_][x_] = true;
-                    }
-                    Status::Moved => {
-                        let new = [path.clone(), vec![Movement::North]].concat();
-                        stack.push_back((new, (x, y - 1)));
+                let is_oxygen = status == Status::Found;
+                if oxygen.is_none() && is_oxygen && queue.is_empty() {
+                    oxygen = Some(target);
+                    distance = Some(path.len());
+                } else if (oxygen == Some(target)) != is_oxygen {
+                    return Err(DroidError::OxygenMismatch(target));
+                }
 
-                        let new = [path.clone(), vec![Movement::South]].concat();
-                        stack.push_back((new, (x, y + 1)));
+                open.insert(target);
+                droid = target;
+            }
+        }
+
+        if queue.is_empty() {
+            if animate {
+                draw_map(&map, &seen, x, y, oxygen);
+            }
+            if status == Status::Moved {
+                let new = [path.clone(), vec![Movement::North]].concat();
+                stack.push_back((new, (x, y - 1)));
+
+                let new = [path.clone(), vec![Movement::South]].concat();
+                stack.push_back((new, (x, y + 1)));
 
-                        let new = [path.clone(), vec![Movement::West]].concat();
-                        stack.push_back((new, (x - 1, y)));
+                let new = [path.clone(), vec![Movement::West]].concat();
+                stack.push_back((new, (x - 1, y)));
 
-                        let new = [path.clone(), vec![Movement::East]].concat();
-                        stack.push_back((new, (x + 1, y)));
+                let new = [path.clone(), vec![Movement::East]].concat();
+                stack.push_back((new, (x + 1, y)));
+            }
+
+            while queue.is_empty() {
+                if let Some((path_, (x_, y_))) = stack.pop_front() {
+                    path = path_;
+                    x = x_;
+                    y = y_;
+
+                    if seen.insert((x, y)) {
+                        interpreter.reset(memory);
+                        droid = (0, 0);
+                        queue.extend(&path);
                     }
-                    Status::Found => {
-                        if distance.is_none() {
-                            oxygen = Some((x, y));
-                            distance = Some(path.len());
-                        }
+                } else {
+                    let maze = Maze {
+                        map,
+                        open,
+                        oxygen: oxygen.unwrap(),
+                    };
+                    return Ok(MazeResult::new(distance.unwrap(), maze));
+                }
+            }
+        }
+
+        movement = queue.pop_front().unwrap();
+        interpreter.provide_input((&movement).into());
+    }
+
+    Err(stopped(&interpreter))
+}
+
+// Sends the droid one movement command and returns what it reports back.
+fn try_move<M: Machine>(droid: &mut M, movement: &Movement) -> Result<Status, DroidError> {
+    droid.provide_input(movement.into());
+    match droid.next_output()? {
+        Some(out) => Ok(Status::from(out)),
+        None => Err(stopped(droid)),
+    }
+}
+
+// Explores breadth-first, restoring a snapshot of the droid at each open cell
+// rather than replaying the path there from the start. The oxygen system is
+// first found along a shortest path, so its distance is correct right away.
+fn explore_bfs(memory: &[i64]) -> Result<MazeResult, DroidError> {
+    let mut interpreter = Interpreter::new(memory);
+    interpreter.echo_output = false;
+
+    let mut map = vec![vec![false; 50]; 50];
+    let mut open = HashSet::new();
+    open.insert((0, 0));
+
+    let mut oxygen = None;
+    let mut distance = None;
+
+    let mut queue = VecDeque::from(vec![((0, 0), 0, interpreter.snapshot())]);
+    while let Some(((x, y), length, snapshot)) = queue.pop_front() {
+        for movement in &[
+            Movement::North,
+            Movement::South,
+            Movement::West,
+            Movement::East,
+        ] {
+            let (dx, dy) = movement.coords();
+            let target = (x + dx, y + dy);
+            if open.contains(&target) || map[(target.1 + 25) as usize][(target.0 + 25) as usize] {
+                continue;
+            }
+
+            interpreter.restore(&snapshot);
+            let status = try_move(&mut interpreter, movement)?;
+
+            match status {
+                Status::HitWall => map[(target.1 + 25) as usize][(target.0 + 25) as usize] = true,
+                Status::Moved | Status::Found => {
+                    if status == Status::Found && oxygen.is_none() {
+                        oxygen = Some(target);
+                        distance = Some(length + 1);
                     }
+
+                    open.insert(target);
+                    queue.push_back((target, length + 1, interpreter.snapshot()));
                 }
+            }
+        }
+    }
 
-                while queue.is_empty() {
-                    if let Some((path_, (x_, y_))) = stack.pop_front() {
-                        path = path_;
-                        x = x_;
-                        y = y_;
+    let maze = Maze {
+        map,
+        open,
+        oxygen: oxygen.unwrap(),
+    };
+    Ok(MazeResult::new(distance.unwrap(), maze))
+}
 
-                        if seen.insert((x, y)) {
-                            interpreter.reset(memory);
-                            queue.extend(&path);
-                        }
-                    } else {
-                        return (distance.unwrap(), map, oxygen.unwrap());
+// Explores depth-first with a single droid, taking the first way it hasn't
+// tried yet and backing out of dead ends with `Movement::reverse`. The droid doesn't know
+// how far it is from the start, so the shortest distance is worked out from
+// the finished maze.
+fn explore_backtracking(memory: &[i64]) -> Result<MazeResult, DroidError> {
+    let mut droid = Interpreter::new(memory);
+    droid.echo_output = false;
+
+    let mut map = vec![vec![false; 50]; 50];
+    let mut open = HashSet::new();
+    open.insert((0, 0));
+    let mut oxygen = None;
+
+    let mut position: (i64, i64) = (0, 0);
+    let mut trail: Vec<Movement> = Vec::new();
+    loop {
+        let (x, y) = position;
+        let untried = [
+            Movement::North,
+            Movement::South,
+            Movement::West,
+            Movement::East,
+        ]
+        .iter()
+        .map(|movement| {
+            let (dx, dy) = movement.coords();
+            (*movement, (x + dx, y + dy))
+        })
+        .find(|(_, target)| {
+            !open.contains(target) && !map[(target.1 + 25) as usize][(target.0 + 25) as usize]
+        });
+
+        match untried {
+            Some((movement, target)) => match try_move(&mut droid, &movement)? {
+                Status::HitWall => map[(target.1 + 25) as usize][(target.0 + 25) as usize] = true,
+                status => {
+                    if status == Status::Found {
+                        oxygen = Some(target);
                     }
+                    open.insert(target);
+                    position = target;
+                    trail.push(movement);
+                }
+            },
+
+            None => {
+                let back = match trail.pop() {
+                    Some(movement) => movement.reverse(),
+                    None => break,
+                };
+                let (dx, dy) = back.coords();
+                let target = (x + dx, y + dy);
+                if try_move(&mut droid, &back)? == Status::HitWall {
+                    return Err(DroidError::HitWallInOpenCell(target));
                 }
+                position = target;
             }
+        }
+    }
+
+    let maze = Maze {
+        map,
+        open,
+        oxygen: oxygen.unwrap(),
+    };
+    let distance = maze.distances()[&maze.oxygen()];
+    Ok(MazeResult::new(distance, maze))
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Strategy {
+    Replay,
+    Bfs,
+    Backtrack,
+}
 
-            let movement = &queue.pop_front().unwrap();
-            tx_input.send(movement.into()).unwrap();
+impl Strategy {
+    fn parse(name: &str) -> Self {
+        match name {
+            "replay" => Strategy::Replay,
+            "bfs" => Strategy::Bfs,
+            "backtrack" => Strategy::Backtrack,
+            _ => panic!(
+                "unknown strategy {}, expected replay, bfs or backtrack",
+                name
+            ),
         }
     }
+}
 
-    panic!();
+fn explore(memory: &[i64], strategy: Strategy, animate: bool) -> Result<MazeResult, DroidError> {
+    match strategy {
+        Strategy::Replay => part1(memory, animate),
+        Strategy::Bfs => explore_bfs(memory),
+        Strategy::Backtrack => explore_backtracking(memory),
+    }
 }
 
-fn part2(map: &Vec<Vec<bool>>, (x, y): (i64, i64)) -> i64 {
+fn part2(maze: &Maze) -> i64 {
+    let map = &maze.map;
+    let (x, y) = maze.oxygen();
     let x = (x + 25) as usize;
     let y = (y + 25) as usize;
     let mut stack = vec![((x, y), 0)];
     let mut seen = HashSet::new();
     let mut max = 0;
-    while !stack.is_empty() {
-        let ((x, y), length) = stack.pop().unwrap();
+    while let Some(((x, y), length)) = stack.pop() {
         if !seen.insert((x, y)) || map[y][x] {
             continue;
         }
@@ -484,18 +555,250 @@ fn part2(map: &Vec<Vec<bool>>, (x, y): (i64, i64)) -> i64 {
     max
 }
 
-fn main() {
-    let file = File::open(std::env::args().nth(1).expect("no filename provided")).unwrap();
-    let mut input = String::new();
-    BufReader::new(file).read_line(&mut input).unwrap();
+fn main() -> Result<(), DroidError> {
+    let mut filename = None;
+    let mut strategy = Strategy::Replay;
+    let mut heatmap = false;
+    let mut animate = false;
+
+    // `--strategy replay|bfs|backtrack` picks how the maze is explored. `--animate`
+    // draws the map while exploring, for replay only.
+    let mut args = output::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--strategy" => {
+                strategy = Strategy::parse(&args.next().expect("--strategy needs a name"))
+            }
+            "--bfs" => strategy = Strategy::Bfs,
+            "--heatmap" => heatmap = true,
+            "--animate" => animate = true,
+            _ => filename = Some(arg),
+        }
+    }
+
+    let memory = input::read_program(filename)?;
+
+    let result = explore(&memory, strategy, animate)?;
+    let maze = &result.maze;
+    if heatmap {
+        maze.render_distances(&mut std::io::stdout()).unwrap();
+    }
+
+    answer(1, result.shortest_distance);
+    sayln!("open cells: {}", maze.open_cells().len());
+    sayln!("oxygen system: {:?} from {:?}", result.oxygen, result.start);
+    answer(2, part2(maze));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aoc2019::intcode::machine::{ScriptedMachine, StepResult};
+
+    const MAZE: &[&str] = &[
+        "#########",
+        "#S..#...#",
+        "#.#.#.#.#",
+        "#.#...#O#",
+        "#.#####.#",
+        "#.......#",
+        "#########",
+    ];
+
+    // A droid program for a maze drawn with `#` for walls, `S` for the start and
+    // `O` for the oxygen system. It keeps the droid's position as an index into
+    // the maze, and patches its own instructions to look up the offset for each
+    // command and the cell it leads to.
+    fn maze_program(maze: &[&str]) -> Vec<i64> {
+        let width = maze[0].len() as i64;
+        let cells: Vec<i64> = maze
+            .iter()
+            .flat_map(|row| row.chars())
+            .map(|c| match c {
+                '#' => 0,
+                'O' => 2,
+                _ => 1,
+            })
+            .collect();
+        let start = maze.concat().find('S').unwrap() as i64;
+
+        let (command, position, next, cell, offsets, grid) = (35, 36, 37, 38, 39, 44);
+        let code: &[&[i64]] = &[
+            // 0: read a command and point the add below at its offset.
+            &[3, command],
+            &[1001, command, offsets, 7],
+            // 6: work out where it leads, and point the copy below at that cell.
+            &[1, 0, position, next],
+            &[1001, next, grid, 15],
+            &[1001, 0, 0, cell],
+            // 18: report a wall without moving.
+            &[1005, cell, 26],
+            &[104, 0],
+            &[1105, 1, 0],
+            // 26: move, and report what's there.
+            &[1001, next, 0, position],
+            &[4, cell],
+            &[1105, 1, 0],
+            // 35: command, position, next, cell and the offsets for each command.
+            &[0, start, 0, 0],
+            &[0, -width, width, -1, 1],
+            &cells,
+        ];
+        code.concat()
+    }
 
-    let memory: Vec<i64> = input
-        .split(',')
-        .map(|x| x.trim().parse().unwrap())
-        .collect();
+    // Every open cell and the oxygen system, relative to the start.
+    fn expected(maze: &[&str]) -> (HashSet<(i64, i64)>, (i64, i64)) {
+        let cells: Vec<((i64, i64), char)> = maze
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.chars()
+                    .enumerate()
+                    .map(move |(x, c)| ((x as i64, y as i64), c))
+            })
+            .collect();
+        let find = |wanted| cells.iter().find(|(_, c)| *c == wanted).unwrap().0;
+        let (sx, sy) = find('S');
+        let (ox, oy) = find('O');
+
+        let open = cells
+            .iter()
+            .filter(|(_, c)| *c != '#')
+            .map(|((x, y), _)| (x - sx, y - sy))
+            .collect();
+        (open, (ox - sx, oy - sy))
+    }
 
-    let (part1, map, oxygen) = part1(&memory);
-    dbg!(part1);
-    let part2 = part2(&map, oxygen);
-    dbg!(part2);
+    #[test]
+    fn open_cells() {
+        let result = part1(&maze_program(MAZE), false).unwrap();
+        let open = result.maze.open_cells();
+        assert!(open.contains(&(0, 0)));
+        assert!(open.contains(&result.maze.oxygen()));
+        assert!(!open.contains(&(-1, 0)));
+        assert!(!open.contains(&(3, 0)));
+        assert_eq!((open, result.maze.oxygen()), expected(MAZE));
+    }
+
+    #[test]
+    fn maze_result_fields() {
+        let result = part1(&maze_program(MAZE), false).unwrap();
+        assert_eq!(result.shortest_distance, 12);
+        assert_eq!(result.oxygen, (6, 2));
+        assert_eq!(result.start, (0, 0));
+        assert_eq!(result.maze.oxygen(), result.oxygen);
+        assert_eq!(result.maze.open_cells().len(), expected(MAZE).0.len());
+    }
+
+    #[test]
+    fn breadth_first_distance() {
+        let result = explore_bfs(&maze_program(MAZE)).unwrap();
+        let distances = result.maze.distances();
+        assert_eq!(result.shortest_distance, distances[&result.maze.oxygen()]);
+        assert_eq!(result.shortest_distance, 12);
+    }
+
+    #[test]
+    fn strategies_agree() {
+        let memory = maze_program(MAZE);
+        for strategy in &[Strategy::Replay, Strategy::Bfs, Strategy::Backtrack] {
+            let result = explore(&memory, *strategy, false).unwrap();
+            let found = (result.maze.open_cells(), result.maze.oxygen());
+            assert_eq!(found, expected(MAZE), "{:?}", strategy);
+            assert_eq!(result.shortest_distance, 12, "{:?}", strategy);
+        }
+    }
+
+    #[test]
+    fn distance_heatmap() {
+        let result = part1(&maze_program(MAZE), false).unwrap();
+        let mut rendered = Vec::new();
+        result.maze.render_distances(&mut rendered).unwrap();
+        let rendered = String::from_utf8(rendered).unwrap();
+
+        // Every cell is two characters wide, and the start is at 25,25.
+        let rows: Vec<Vec<char>> = rendered.lines().map(|row| row.chars().collect()).collect();
+        let cell = |x: i64, y: i64| -> String {
+            let (x, y) = ((x + 25) as usize, (y + 25) as usize);
+            rows[y][x * 2..x * 2 + 2].iter().collect()
+        };
+        assert_eq!(cell(0, 0), " 0");
+        assert_eq!(cell(1, 0), " 1");
+        assert_eq!(cell(2, 0), " 2");
+        assert_eq!(cell(2, 2), " 4");
+        assert_eq!(cell(0, 4), " 4");
+        assert_eq!(cell(-1, 0), "██");
+    }
+
+    #[test]
+    fn render_cells() {
+        let grid = vec![
+            vec![Cell::Wall, Cell::Oxygen, Cell::Droid],
+            vec![Cell::Start, Cell::Open, Cell::Unknown],
+        ];
+        assert_eq!(render_grid(&grid), "██⛳🦀\n🚦  ▒▒\n");
+    }
+
+    #[test]
+    fn inconsistent_status() {
+        // Moves once after being started, and hits a wall every time after
+        // that, including when backing into the cell it just left.
+        let memory = vec![3, 20, 104, 1, 3, 20, 104, 0, 1105, 1, 4];
+        assert!(matches!(
+            part1(&memory, false),
+            Err(DroidError::HitWallInOpenCell((0, 0)))
+        ));
+    }
+
+    #[test]
+    fn scripted_droid() {
+        let script = [0, 1, 2]
+            .iter()
+            .map(|&status| StepResult::Output(status))
+            .collect();
+        let mut droid = ScriptedMachine::new(script);
+        assert_eq!(
+            try_move(&mut droid, &Movement::North).unwrap(),
+            Status::HitWall
+        );
+        assert_eq!(
+            try_move(&mut droid, &Movement::East).unwrap(),
+            Status::Moved
+        );
+        assert_eq!(
+            try_move(&mut droid, &Movement::South).unwrap(),
+            Status::Found
+        );
+        assert!(matches!(
+            try_move(&mut droid, &Movement::West),
+            Err(DroidError::Halted)
+        ));
+        assert_eq!(droid.inputs, vec![1, 4, 2, 3]);
+    }
+
+    #[test]
+    fn droid_wants_more_input() {
+        // Reads two values before it reports a status.
+        let memory = vec![3, 20, 3, 20, 104, 1, 99];
+        assert!(matches!(part1(&memory, false), Err(DroidError::NeedsInput)));
+
+        let mut droid = Interpreter::new(&memory);
+        droid.echo_output = false;
+        assert!(matches!(
+            try_move(&mut droid, &Movement::North),
+            Err(DroidError::NeedsInput)
+        ));
+        droid.provide_input(1);
+        assert!(matches!(
+            try_move(&mut droid, &Movement::North),
+            Ok(Status::Moved)
+        ));
+        assert!(matches!(
+            try_move(&mut droid, &Movement::North),
+            Err(DroidError::Halted)
+        ));
+    }
 }
@@ -1,5 +1,8 @@
-fn part1() -> String {
-    let input_str = "59731816011884092945351508129673371014862103878684944826017645844741545300230138932831133873839512146713127268759974246245502075014905070039532876129205215417851534077861438833829150700128859789264910166202535524896960863759734991379392200570075995540154404564759515739872348617947354357737896622983395480822393561314056840468397927687908512181180566958267371679145705350771757054349846320639601111983284494477902984330803048219450650034662420834263425046219982608792077128250835515865313986075722145069152768623913680721193045475863879571787112159970381407518157406924221437152946039000886837781446203456224983154446561285113664381711600293030463013";
+use aoc2019::input::{self, InputError};
+use aoc2019::output::{self, answer};
+use std::io::Read;
+
+fn part1(input_str: &str) -> String {
     let mut input: Vec<i32> = input_str
         .chars()
         .map(|i| i.to_digit(10).unwrap() as i32)
@@ -13,8 +16,7 @@ fn part1() -> String {
                     .zip(
                         [0, 1, 0, -1]
                             .iter()
-                            .map(|i| vec![i; number])
-                            .flatten()
+                            .flat_map(|i| vec![i; number])
                             .cycle()
                             .skip(1),
                     )
@@ -33,8 +35,7 @@ fn part1() -> String {
         .join("")
 }
 
-fn part2() -> String {
-    let input_str = "59731816011884092945351508129673371014862103878684944826017645844741545300230138932831133873839512146713127268759974246245502075014905070039532876129205215417851534077861438833829150700128859789264910166202535524896960863759734991379392200570075995540154404564759515739872348617947354357737896622983395480822393561314056840468397927687908512181180566958267371679145705350771757054349846320639601111983284494477902984330803048219450650034662420834263425046219982608792077128250835515865313986075722145069152768623913680721193045475863879571787112159970381407518157406924221437152946039000886837781446203456224983154446561285113664381711600293030463013";
+fn part2(input_str: &str) -> String {
     let offset: usize = input_str[..7].parse::<usize>().unwrap();
     let mut input: Vec<i32> = input_str
         .chars()
@@ -61,7 +62,15 @@ fn part2() -> String {
         .join("")
 }
 
-fn main() {
-    dbg!(part1());
-    dbg!(part2());
+fn main() -> Result<(), InputError> {
+    let mut input = String::new();
+    input::reader(output::args().nth(1))?
+        .read_to_string(&mut input)
+        .unwrap();
+    let input = input.trim();
+
+    answer(1, part1(input));
+    answer(2, part2(input));
+
+    Ok(())
 }
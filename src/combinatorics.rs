@@ -0,0 +1,139 @@
+// Every ordering of some items. They come out in lexicographic order of
+// position, so in lexicographic order if the items are sorted.
+pub struct Permutations<T> {
+    items: Vec<T>,
+    indices: Vec<usize>,
+    done: bool,
+}
+
+pub fn permutations<T: Clone>(items: &[T]) -> Permutations<T> {
+    Permutations {
+        items: items.to_vec(),
+        indices: (0..items.len()).collect(),
+        done: false,
+    }
+}
+
+impl<T: Clone> Iterator for Permutations<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        if self.done {
+            return None;
+        }
+        let permutation = self
+            .indices
+            .iter()
+            .map(|&i| self.items[i].clone())
+            .collect();
+
+        // Finds the rightmost index that's smaller than the one after it, swaps
+        // it with the rightmost index bigger than it, and reverses the tail.
+        let indices = &mut self.indices;
+        match (1..indices.len())
+            .rev()
+            .find(|&i| indices[i - 1] < indices[i])
+        {
+            Some(i) => {
+                let j = (i..indices.len())
+                    .rev()
+                    .find(|&j| indices[j] > indices[i - 1]);
+                indices.swap(i - 1, j.unwrap());
+                indices[i..].reverse();
+            }
+            None => self.done = true,
+        }
+
+        Some(permutation)
+    }
+}
+
+// Every way to pick `k` of some items, keeping them in their original order.
+pub struct Combinations<T> {
+    items: Vec<T>,
+    indices: Vec<usize>,
+    done: bool,
+}
+
+pub fn combinations<T: Clone>(items: &[T], k: usize) -> Combinations<T> {
+    Combinations {
+        items: items.to_vec(),
+        indices: (0..k).collect(),
+        done: k > items.len(),
+    }
+}
+
+impl<T: Clone> Iterator for Combinations<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        if self.done {
+            return None;
+        }
+        let combination = self
+            .indices
+            .iter()
+            .map(|&i| self.items[i].clone())
+            .collect();
+
+        // Bumps the rightmost index that still has room to move, and puts the
+        // ones after it right behind it.
+        let (n, k) = (self.items.len(), self.indices.len());
+        let indices = &mut self.indices;
+        match (0..k).rev().find(|&i| indices[i] < n - k + i) {
+            Some(i) => {
+                indices[i] += 1;
+                for j in i + 1..k {
+                    indices[j] = indices[j - 1] + 1;
+                }
+            }
+            None => self.done = true,
+        }
+
+        Some(combination)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn factorial(n: usize) -> usize {
+        (1..=n).product()
+    }
+
+    #[test]
+    fn permutation_counts() {
+        for n in 0..=6 {
+            let items: Vec<usize> = (0..n).collect();
+            let all: Vec<Vec<usize>> = permutations(&items).collect();
+            assert_eq!(all.len(), factorial(n));
+
+            let unique: HashSet<_> = all.iter().collect();
+            assert_eq!(unique.len(), all.len());
+            assert!(all.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+    }
+
+    #[test]
+    fn combination_counts() {
+        let items: Vec<usize> = (0..6).collect();
+        for k in 0..=6 {
+            let all: Vec<Vec<usize>> = combinations(&items, k).collect();
+            assert_eq!(all.len(), factorial(6) / (factorial(k) * factorial(6 - k)));
+
+            let unique: HashSet<_> = all.iter().collect();
+            assert_eq!(unique.len(), all.len());
+            assert!(all.iter().all(|picked| picked.len() == k));
+            assert!(all
+                .iter()
+                .all(|picked| picked.windows(2).all(|pair| pair[0] < pair[1])));
+        }
+        assert_eq!(combinations(&items, 7).count(), 0);
+        assert_eq!(
+            combinations(&['a', 'b', 'c'], 2).collect::<Vec<_>>(),
+            vec![vec!['a', 'b'], vec!['a', 'c'], vec!['b', 'c']]
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use crate::intcode::{IntcodeProgram, ParseError, Word};
+use std::fmt::{Debug, Display, Formatter};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+
+pub enum InputError {
+    NoFilename,
+    Open(String, io::Error),
+    Program(ParseError),
+}
+
+impl From<ParseError> for InputError {
+    fn from(error: ParseError) -> Self {
+        InputError::Program(error)
+    }
+}
+
+impl Display for InputError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            InputError::NoFilename => write!(formatter, "no filename provided"),
+            InputError::Open(path, error) => {
+                write!(formatter, "could not open input: {}: {}", path, error)
+            }
+            InputError::Program(error) => write!(formatter, "{}", error),
+        }
+    }
+}
+
+// A `main` returning an error prints it with Debug, so give that the readable
+// message as well.
+impl Debug for InputError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        Display::fmt(self, formatter)
+    }
+}
+
+impl std::error::Error for InputError {}
+
+pub fn open(path: Option<String>) -> Result<File, InputError> {
+    let path = path.ok_or(InputError::NoFilename)?;
+    File::open(&path).map_err(|error| InputError::Open(path, error))
+}
+
+// Every day reads its puzzle input from the file named on the command line, or
+// from stdin without one.
+pub fn reader(path: Option<String>) -> Result<Box<dyn BufRead>, InputError> {
+    match path {
+        Some(path) => Ok(Box::new(BufReader::new(open(Some(path))?))),
+        None => Ok(Box::new(io::stdin().lock())),
+    }
+}
+
+pub fn read_program(path: Option<String>) -> Result<Vec<Word>, InputError> {
+    Ok(IntcodeProgram::from_reader(reader(path)?)?.into())
+}
@@ -0,0 +1,980 @@
+use crate::intcode::machine::{Machine, StepResult};
+use crate::intcode::memory::{Memory, SparseMemory};
+use crate::intcode::Word;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use std::sync::mpsc::{Receiver, Sender};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    Add,
+    Multiply,
+    Read,
+    Write,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equals,
+    RelativeBase,
+    Halt,
+}
+
+impl From<Word> for Opcode {
+    fn from(item: Word) -> Self {
+        match item {
+            1 => Opcode::Add,
+            2 => Opcode::Multiply,
+            3 => Opcode::Read,
+            4 => Opcode::Write,
+            5 => Opcode::JumpIfTrue,
+            6 => Opcode::JumpIfFalse,
+            7 => Opcode::LessThan,
+            8 => Opcode::Equals,
+            9 => Opcode::RelativeBase,
+            99 => Opcode::Halt,
+            _ => panic!("unknown instruction {}", item),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterMode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+impl From<Word> for ParameterMode {
+    fn from(item: Word) -> Self {
+        match item {
+            0 => ParameterMode::Position,
+            1 => ParameterMode::Immediate,
+            2 => ParameterMode::Relative,
+            _ => panic!("unknown parameter mode {}", item),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Parameter {
+    pub mode: ParameterMode,
+    pub value: Word,
+}
+
+impl Parameter {
+    pub fn new(mode: ParameterMode, value: Word) -> Self {
+        Self { mode, value }
+    }
+
+    // The memory address this parameter refers to, for the instruction at `ip`.
+    // Immediate parameters don't have one.
+    pub fn address(&self, relative_base: Word, ip: Word) -> Result<usize, IntcodeError> {
+        let address = match self.mode {
+            ParameterMode::Position => self.value,
+            ParameterMode::Relative => self
+                .value
+                .checked_add(relative_base)
+                .ok_or(IntcodeError::Overflow { ip })?,
+            ParameterMode::Immediate => return Err(IntcodeError::ImmediateAddress(self.value)),
+        };
+        usize::try_from(address).map_err(|_| IntcodeError::NegativeAddress { address, ip })
+    }
+}
+
+impl Display for Parameter {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self.mode {
+            ParameterMode::Immediate => write!(formatter, "{}", self.value),
+            ParameterMode::Position => write!(formatter, "[{}]", self.value),
+            ParameterMode::Relative => write!(formatter, "rel[{}]", self.value),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub parameters: (Parameter, Parameter, Parameter),
+}
+
+impl Instruction {
+    // None if `ip` is negative or points past the end of memory.
+    pub fn fetch(ip: Word, memory: &impl Memory) -> Option<Self> {
+        let ip = usize::try_from(ip).ok()?;
+        let instruction = memory.get(ip)?;
+
+        let opcode = Opcode::from(instruction % 100);
+        let parameters = (
+            Parameter::new(
+                ParameterMode::from(instruction / 100 % 10),
+                memory.get(ip + 1).unwrap_or(0),
+            ),
+            Parameter::new(
+                ParameterMode::from(instruction / 1000 % 10),
+                memory.get(ip + 2).unwrap_or(0),
+            ),
+            Parameter::new(
+                ParameterMode::from(instruction / 10000 % 10),
+                memory.get(ip + 3).unwrap_or(0),
+            ),
+        );
+
+        Some(Self { opcode, parameters })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum IntcodeError {
+    // The instruction at `ip` referred to a negative address. A negative `ip`
+    // shows up as its own address.
+    NegativeAddress { address: Word, ip: Word },
+    // A jump left `ip` pointing at memory that was never loaded or written.
+    UnmappedIp { ip: Word },
+    ImmediateAddress(Word),
+    // The instruction at `ip` tried to write to an immediate parameter.
+    ImmediateWrite { ip: Word },
+    // The instruction at `ip` tried to read while nothing was queued and stdin
+    // had nothing left.
+    NoInput { ip: Word },
+    // The instruction at `ip` tried to read from `rx` after its sender was
+    // dropped.
+    Disconnected { ip: Word },
+    // The instruction at `ip` pushed the relative base, or an address relative
+    // to it, out of range.
+    Overflow { ip: Word },
+    // In checked mode, the Add or Multiply at `ip` overflowed on these operands.
+    ArithmeticOverflow { ip: Word, operands: (Word, Word) },
+    Timeout,
+}
+
+impl Display for IntcodeError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            IntcodeError::NegativeAddress { address, ip } => {
+                write!(formatter, "negative address {} at ip {}", address, ip)
+            }
+            IntcodeError::UnmappedIp { ip } => write!(formatter, "jumped to unmapped ip {}", ip),
+            IntcodeError::ImmediateAddress(value) => {
+                write!(formatter, "immediate parameter {} has no address", value)
+            }
+            IntcodeError::ImmediateWrite { ip } => {
+                write!(formatter, "write to an immediate parameter at ip {}", ip)
+            }
+            IntcodeError::NoInput { ip } => write!(formatter, "no input to read at ip {}", ip),
+            IntcodeError::Disconnected { ip } => {
+                write!(formatter, "input channel closed at ip {}", ip)
+            }
+            IntcodeError::Overflow { ip } => write!(formatter, "address overflow at ip {}", ip),
+            IntcodeError::ArithmeticOverflow { ip, operands } => write!(
+                formatter,
+                "arithmetic overflow at ip {} on {} and {}",
+                ip, operands.0, operands.1
+            ),
+            IntcodeError::Timeout => write!(formatter, "program ran out of time"),
+        }
+    }
+}
+
+impl std::error::Error for IntcodeError {}
+
+// Everything needed to put an interpreter back the way it was.
+pub struct Snapshot<M: Memory = Vec<Word>> {
+    memory: M,
+    ip: Word,
+    relative_base: Word,
+}
+
+// Input comes from `inputs`, then from `rx`, which blocks until a value is sent,
+// and from stdin if there's no `rx`.
+// Output goes to `outputs`, and then to `tx`, or to stdout if there's no `tx`
+// and `echo_output` is on. Outputs handed back by run_until_output and
+// run_until_input are taken off `outputs` again. Memory is a plain Vec unless asked otherwise.
+pub struct Interpreter<M: Memory = Vec<Word>> {
+    pub memory: M,
+    pub rx: Option<Receiver<Word>>,
+    pub tx: Option<Sender<Word>>,
+    pub inputs: VecDeque<Word>,
+    pub outputs: VecDeque<Word>,
+    pub last_output: Option<Word>,
+    pub ip: Word,
+    pub relative_base: Word,
+    pub debug: bool,
+    // Whether every value the program reads is echoed as if it were output.
+    pub echo_input: bool,
+    pub echo_output: bool,
+    pub cycles: u64,
+    // Whether Add and Multiply fail on overflow instead of wrapping around.
+    pub checked: bool,
+    // Every input the program has read, once set to Some.
+    pub recorded: Option<Vec<Word>>,
+    halted: bool,
+    on_write: Option<Box<dyn FnMut(usize, Word) + Send>>,
+    on_output: Option<Box<dyn FnMut(Word) + Send>>,
+}
+
+impl Interpreter {
+    pub fn new(program: &[Word]) -> Self {
+        Self::with_memory(program.to_vec())
+    }
+}
+
+impl Interpreter<SparseMemory> {
+    pub fn sparse(program: &[Word]) -> Self {
+        Self::with_memory(SparseMemory::load(program))
+    }
+}
+
+impl<M: Memory> Interpreter<M> {
+    pub fn with_memory(memory: M) -> Self {
+        Self {
+            memory,
+            rx: None,
+            tx: None,
+            inputs: VecDeque::new(),
+            outputs: VecDeque::new(),
+            last_output: None,
+            ip: 0,
+            relative_base: 0,
+            debug: false,
+            echo_input: false,
+            echo_output: true,
+            cycles: 0,
+            checked: false,
+            recorded: None,
+            halted: false,
+            on_write: None,
+            on_output: None,
+        }
+    }
+
+    // Called with the address and value of every write to memory.
+    pub fn on_write(&mut self, cb: Box<dyn FnMut(usize, Word) + Send>) {
+        self.on_write = Some(cb);
+    }
+
+    // Called with every value the program outputs.
+    pub fn on_output(&mut self, cb: Box<dyn FnMut(Word) + Send>) {
+        self.on_output = Some(cb);
+    }
+
+    // Queues a value for the program to read.
+    pub fn push_input(&mut self, value: Word) {
+        self.inputs.push_back(value);
+    }
+
+    // Takes the oldest output that hasn't been taken yet.
+    pub fn pop_output(&mut self) -> Option<Word> {
+        self.outputs.pop_front()
+    }
+
+    // Starts over with `program`, dropping any queued input and output.
+    pub fn reset(&mut self, program: &[Word]) {
+        self.memory = M::load(program);
+        self.ip = 0;
+        self.relative_base = 0;
+        self.inputs.clear();
+        self.outputs.clear();
+        self.last_output = None;
+        self.halted = false;
+    }
+
+    pub fn snapshot(&self) -> Snapshot<M> {
+        Snapshot {
+            memory: self.memory.clone(),
+            ip: self.ip,
+            relative_base: self.relative_base,
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot<M>) {
+        self.memory = snapshot.memory.clone();
+        self.ip = snapshot.ip;
+        self.relative_base = snapshot.relative_base;
+        self.inputs.clear();
+        self.outputs.clear();
+        self.halted = false;
+    }
+
+    // A separate interpreter that carries on from where this one is. Only the
+    // program's state and the settings are copied: it starts without channels,
+    // callbacks, queued input or output.
+    pub fn clone_state(&self) -> Self {
+        let mut clone = Self::with_memory(self.memory.clone());
+        clone.ip = self.ip;
+        clone.relative_base = self.relative_base;
+        clone.halted = self.halted;
+        clone.debug = self.debug;
+        clone.echo_input = self.echo_input;
+        clone.echo_output = self.echo_output;
+        clone.checked = self.checked;
+        clone
+    }
+
+    // Overwrites memory from `offset` on with `data`, growing it if needed.
+    pub fn load_at(&mut self, offset: usize, data: &[Word]) {
+        for (i, value) in data.iter().enumerate() {
+            *self.memory.get_mut(offset + i) = *value;
+        }
+    }
+
+    // The instruction at `ip`, or why there isn't one.
+    pub fn instruction(&self) -> Result<Instruction, IntcodeError> {
+        if self.ip < 0 {
+            return Err(IntcodeError::NegativeAddress {
+                address: self.ip,
+                ip: self.ip,
+            });
+        }
+        Instruction::fetch(self.ip, &self.memory).ok_or(IntcodeError::UnmappedIp { ip: self.ip })
+    }
+
+    pub fn step(&mut self) -> Result<bool, IntcodeError> {
+        let instruction = self.instruction()?;
+        let (a, b, c) = &instruction.parameters;
+
+        let (ip, arg_count) = match instruction.opcode {
+            Opcode::Add => {
+                let operands = (self.value(a)?, self.value(b)?);
+                let result = self.arithmetic(operands, Word::checked_add, Word::wrapping_add)?;
+                self.write(c, result)?;
+                (self.ip + 4, 3)
+            }
+
+            Opcode::Multiply => {
+                let operands = (self.value(a)?, self.value(b)?);
+                let result = self.arithmetic(operands, Word::checked_mul, Word::wrapping_mul)?;
+                self.write(c, result)?;
+                (self.ip + 4, 3)
+            }
+
+            Opcode::Read => {
+                let input = self.receive()?;
+                self.write(a, input)?;
+                (self.ip + 2, 1)
+            }
+
+            Opcode::Write => {
+                let value = self.value(a)?;
+                self.outputs.push_back(value);
+                self.last_output = Some(value);
+                if let Some(on_output) = &mut self.on_output {
+                    on_output(value);
+                }
+                self.output(value);
+                (self.ip + 2, 1)
+            }
+
+            Opcode::JumpIfTrue => (
+                if self.value(a)? != 0 {
+                    self.value(b)?
+                } else {
+                    self.ip + 3
+                },
+                2,
+            ),
+
+            Opcode::JumpIfFalse => (
+                if self.value(a)? == 0 {
+                    self.value(b)?
+                } else {
+                    self.ip + 3
+                },
+                2,
+            ),
+
+            Opcode::LessThan => {
+                let result = self.value(a)? < self.value(b)?;
+                self.write(c, if result { 1 } else { 0 })?;
+                (self.ip + 4, 3)
+            }
+
+            Opcode::Equals => {
+                let result = self.value(a)? == self.value(b)?;
+                self.write(c, if result { 1 } else { 0 })?;
+                (self.ip + 4, 3)
+            }
+
+            Opcode::RelativeBase => {
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(self.value(a)?)
+                    .ok_or(IntcodeError::Overflow { ip: self.ip })?;
+                (self.ip + 2, 1)
+            }
+
+            Opcode::Halt => {
+                self.halted = true;
+                self.cycles += 1;
+                return Ok(false);
+            }
+        };
+        self.ip = ip;
+        self.cycles += 1;
+
+        if self.debug {
+            let args = match arg_count {
+                1 => format!("{:?} {}", instruction.opcode, a),
+                2 => format!("{:?} {}, {}", instruction.opcode, a, b),
+                3 => format!("{:?} {}, {}, {}", instruction.opcode, a, b, c),
+                _ => panic!(),
+            };
+
+            crate::sayln!(
+                "\rip={:<5} rb={:<5} | {:<30} | {}",
+                ip,
+                self.relative_base,
+                args,
+                self.memory.size()
+            );
+        }
+
+        Ok(true)
+    }
+
+    pub fn run(&mut self) -> Result<(), IntcodeError> {
+        while self.step()? {}
+        Ok(())
+    }
+
+    // Like run, but gives up once `limit` has passed. The clock is only checked
+    // every thousand steps.
+    pub fn run_timed(&mut self, limit: Duration) -> Result<(), IntcodeError> {
+        let start = Instant::now();
+        loop {
+            for _ in 0..1000 {
+                if !self.step()? {
+                    return Ok(());
+                }
+            }
+
+            if start.elapsed() > limit {
+                return Err(IntcodeError::Timeout);
+            }
+        }
+    }
+
+    // Steps until `pred` holds, which is checked before every instruction.
+    // Returns false if the program halted first.
+    pub fn step_until(&mut self, pred: impl Fn(&Self) -> bool) -> Result<bool, IntcodeError> {
+        while !pred(self) {
+            if !self.step()? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // Runs until the program outputs a value and returns it, or None once it
+    // halts.
+    pub fn run_until_output(&mut self) -> Result<Option<Word>, IntcodeError> {
+        loop {
+            let opcode = self.instruction()?.opcode;
+            if !self.step()? {
+                return Ok(None);
+            }
+            if opcode == Opcode::Write {
+                return Ok(self.outputs.pop_back());
+            }
+        }
+    }
+
+    // Runs until the program outputs a value, halts or wants to read something
+    // that hasn't been given yet. In that last case the read hasn't happened,
+    // so it's retried once input has been provided.
+    pub fn run_until_input(&mut self) -> Result<StepResult, IntcodeError> {
+        loop {
+            let opcode = self.instruction()?.opcode;
+            if opcode == Opcode::Read && self.inputs.is_empty() && self.rx.is_none() {
+                return Ok(StepResult::NeedInput);
+            }
+
+            if !self.step()? {
+                return Ok(StepResult::Halted);
+            }
+            if opcode == Opcode::Write {
+                return Ok(StepResult::Output(self.outputs.pop_back().unwrap()));
+            }
+        }
+    }
+
+    // Runs until the program has output `limit` values or halted, and returns
+    // what it output along the way.
+    pub fn run_collecting(&mut self, limit: usize) -> Result<Vec<Word>, IntcodeError> {
+        let mut outputs = Vec::new();
+        while outputs.len() < limit {
+            match self.run_until_output()? {
+                Some(value) => outputs.push(value),
+                None => break,
+            }
+        }
+        Ok(outputs)
+    }
+
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+
+    pub fn read(&self, address: usize) -> Word {
+        self.memory.get(address).unwrap_or(0)
+    }
+
+    fn arithmetic(
+        &self,
+        (a, b): (Word, Word),
+        checked: fn(Word, Word) -> Option<Word>,
+        wrapping: fn(Word, Word) -> Word,
+    ) -> Result<Word, IntcodeError> {
+        if !self.checked {
+            return Ok(wrapping(a, b));
+        }
+
+        checked(a, b).ok_or(IntcodeError::ArithmeticOverflow {
+            ip: self.ip,
+            operands: (a, b),
+        })
+    }
+
+    fn receive(&mut self) -> Result<Word, IntcodeError> {
+        let input = match (self.inputs.pop_front(), &self.rx) {
+            (Some(input), _) => input,
+            (None, Some(rx)) => rx
+                .recv()
+                .map_err(|_| IntcodeError::Disconnected { ip: self.ip })?,
+            (None, None) => {
+                let mut line = String::new();
+                let _ = std::io::stdin().read_line(&mut line);
+                line.trim()
+                    .parse()
+                    .map_err(|_| IntcodeError::NoInput { ip: self.ip })?
+            }
+        };
+
+        if self.echo_input {
+            if self.tx.is_none() {
+                self.outputs.push_back(input);
+            }
+            self.output(input);
+        }
+
+        if let Some(recorded) = &mut self.recorded {
+            recorded.push(input);
+        }
+        Ok(input)
+    }
+
+    fn output(&self, value: Word) {
+        match &self.tx {
+            Some(tx) => {
+                let _ = tx.send(value);
+            }
+            None if self.echo_output => crate::sayln!("> {}", value),
+            None => {}
+        }
+    }
+
+    pub fn value(&self, parameter: &Parameter) -> Result<Word, IntcodeError> {
+        if parameter.mode == ParameterMode::Immediate {
+            return Ok(parameter.value);
+        }
+
+        Ok(self.read(parameter.address(self.relative_base, self.ip)?))
+    }
+
+    fn write(&mut self, parameter: &Parameter, value: Word) -> Result<(), IntcodeError> {
+        *self.value_mut(parameter)? = value;
+        let index = parameter.address(self.relative_base, self.ip)?;
+        if let Some(on_write) = &mut self.on_write {
+            on_write(index, value);
+        }
+        Ok(())
+    }
+
+    pub fn value_mut<'a>(
+        &'a mut self,
+        parameter: &Parameter,
+    ) -> Result<&'a mut Word, IntcodeError> {
+        if parameter.mode == ParameterMode::Immediate {
+            return Err(IntcodeError::ImmediateWrite { ip: self.ip });
+        }
+
+        let index = parameter.address(self.relative_base, self.ip)?;
+        Ok(self.memory.get_mut(index))
+    }
+}
+
+impl<M: Memory> Machine for Interpreter<M> {
+    fn next_output(&mut self) -> Result<Option<Word>, IntcodeError> {
+        match self.run_until_input()? {
+            StepResult::Output(value) => Ok(Some(value)),
+            StepResult::NeedInput | StepResult::Halted => Ok(None),
+        }
+    }
+
+    fn provide_input(&mut self, value: Word) {
+        self.push_input(value);
+    }
+
+    fn is_halted(&self) -> bool {
+        self.halted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    #[test]
+    fn decode_modes() {
+        let modes = |instruction: &Instruction| {
+            let (a, b, c) = &instruction.parameters;
+            (a.mode, b.mode, c.mode)
+        };
+
+        let instruction = Instruction::fetch(0, &vec![1002, 4, 3, 4]).unwrap();
+        assert_eq!(instruction.opcode, Opcode::Multiply);
+        assert_eq!(
+            modes(&instruction),
+            (
+                ParameterMode::Position,
+                ParameterMode::Immediate,
+                ParameterMode::Position
+            )
+        );
+        let (a, b, c) = &instruction.parameters;
+        assert_eq!((a.value, b.value, c.value), (4, 3, 4));
+
+        let instruction = Instruction::fetch(1, &vec![99, 21107, 1, 2, 3]).unwrap();
+        assert_eq!(
+            modes(&instruction),
+            (
+                ParameterMode::Immediate,
+                ParameterMode::Immediate,
+                ParameterMode::Relative
+            )
+        );
+        assert!(Instruction::fetch(5, &vec![99, 21107, 1, 2, 3]).is_none());
+    }
+
+    #[test]
+    fn parameter_addresses() {
+        let position = Parameter::new(ParameterMode::Position, 7);
+        assert_eq!(position.address(100, 0), Ok(7));
+        let position = Parameter::new(ParameterMode::Position, -7);
+        assert_eq!(
+            position.address(100, 3),
+            Err(IntcodeError::NegativeAddress { address: -7, ip: 3 })
+        );
+
+        let relative = Parameter::new(ParameterMode::Relative, -7);
+        assert_eq!(relative.address(100, 0), Ok(93));
+        assert_eq!(
+            relative.address(5, 3),
+            Err(IntcodeError::NegativeAddress { address: -2, ip: 3 })
+        );
+        let relative = Parameter::new(ParameterMode::Relative, 1);
+        assert_eq!(
+            relative.address(Word::MAX, 3),
+            Err(IntcodeError::Overflow { ip: 3 })
+        );
+
+        let immediate = Parameter::new(ParameterMode::Immediate, 7);
+        assert_eq!(
+            immediate.address(100, 0),
+            Err(IntcodeError::ImmediateAddress(7))
+        );
+    }
+
+    #[test]
+    fn on_output_fires_for_every_output() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_ = seen.clone();
+        let mut interpreter = Interpreter::new(&[104, 1, 104, 2, 104, 3, 99]);
+        interpreter.echo_output = false;
+        interpreter.on_output(Box::new(move |value| seen_.lock().unwrap().push(value)));
+
+        interpreter.run().unwrap();
+        assert_eq!(*seen.lock().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn echo_input_lands_in_outputs() {
+        let mut interpreter = Interpreter::new(&[3, 0, 104, 7, 99]);
+        interpreter.echo_input = true;
+        interpreter.echo_output = false;
+        interpreter.push_input(5);
+
+        interpreter.run().unwrap();
+        assert_eq!(interpreter.outputs, vec![5, 7]);
+    }
+
+    #[test]
+    fn any_word_width() {
+        let big = Word::MAX / 3;
+        let mut interpreter = Interpreter::new(&[1101, big, big, 0, 1102, big, 2, 1, 99]);
+        interpreter.run().unwrap();
+        assert_eq!(interpreter.read(0), big * 2);
+        assert_eq!(interpreter.read(1), big * 2);
+
+        let mut interpreter = Interpreter::new(&[109, Word::MIN, 99]);
+        interpreter.run().unwrap();
+        assert_eq!(interpreter.relative_base, Word::MIN);
+    }
+
+    #[test]
+    fn checked_overflow() {
+        let big = Word::MAX / 2 + 1;
+        let program = [1101, 0, 0, 0, 1102, big, 2, 0, 99];
+
+        let mut interpreter = Interpreter::new(&program);
+        interpreter.run().unwrap();
+        assert_eq!(interpreter.read(0), big.wrapping_mul(2));
+
+        let mut interpreter = Interpreter::new(&program);
+        interpreter.checked = true;
+        assert_eq!(
+            interpreter.run(),
+            Err(IntcodeError::ArithmeticOverflow {
+                ip: 4,
+                operands: (big, 2)
+            })
+        );
+        assert_eq!(interpreter.read(0), 0);
+    }
+
+    #[test]
+    fn cloned_state_runs_independently() {
+        // Counts up from 1 in address 20, outputting every number.
+        let mut original = Interpreter::new(&[1001, 20, 1, 20, 4, 20, 1105, 1, 0]);
+        original.echo_output = false;
+        original.checked = true;
+        assert_eq!(original.run_until_output(), Ok(Some(1)));
+
+        let (tx, _rx) = channel();
+        original.tx = Some(tx);
+        original.push_input(5);
+
+        let mut clone = original.clone_state();
+        assert!(clone.tx.is_none());
+        assert!(clone.inputs.is_empty());
+        assert!(clone.checked);
+        assert_eq!(clone.run_until_output(), Ok(Some(2)));
+        assert_eq!(clone.run_until_output(), Ok(Some(3)));
+
+        original.tx = None;
+        assert_eq!(original.read(20), 1);
+        assert_eq!(original.run_until_output(), Ok(Some(2)));
+        assert_eq!(clone.read(20), 3);
+    }
+
+    #[test]
+    fn run_timed_gives_up() {
+        let mut interpreter = Interpreter::new(&[1105, 1, 0]);
+        let start = Instant::now();
+        assert_eq!(
+            interpreter.run_timed(Duration::from_millis(50)),
+            Err(IntcodeError::Timeout)
+        );
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        let mut interpreter = Interpreter::new(&[1101, 2, 3, 0, 99]);
+        assert_eq!(interpreter.run_timed(Duration::from_millis(50)), Ok(()));
+        assert_eq!(interpreter.read(0), 5);
+    }
+
+    #[test]
+    fn step_until_reaches_target() {
+        let mut interpreter = Interpreter::new(&[1101, 2, 3, 0, 109, 5, 1101, 1, 1, 20, 99]);
+        assert_eq!(interpreter.step_until(|i| i.ip == 6), Ok(true));
+        assert_eq!(interpreter.read(0), 5);
+        assert_eq!(interpreter.relative_base, 5);
+        assert_eq!(interpreter.read(20), 0);
+
+        assert_eq!(interpreter.step_until(|i| i.ip == 100), Ok(false));
+        assert_eq!(interpreter.read(20), 2);
+    }
+
+    #[test]
+    fn run_until_output_stops_at_each_output() {
+        let mut interpreter = Interpreter::new(&[104, 1, 104, 2, 99]);
+        interpreter.echo_output = false;
+        assert_eq!(interpreter.run_until_output(), Ok(Some(1)));
+        assert_eq!(interpreter.run_until_output(), Ok(Some(2)));
+        assert_eq!(interpreter.run_until_output(), Ok(None));
+        assert!(interpreter.outputs.is_empty());
+    }
+
+    #[test]
+    fn run_until_output_reports_errors() {
+        let mut interpreter = Interpreter::new(&[1101, 1, 1, -1, 99]);
+        assert_eq!(
+            interpreter.run_until_output(),
+            Err(IntcodeError::NegativeAddress { address: -1, ip: 0 })
+        );
+    }
+
+    #[test]
+    fn relative_base_overflow() {
+        let mut interpreter = Interpreter::new(&[109, Word::MAX - 1, 109, 1, 109, 1, 99]);
+        assert_eq!(interpreter.step(), Ok(true));
+        assert_eq!(interpreter.step(), Ok(true));
+        assert_eq!(interpreter.relative_base, Word::MAX);
+        assert_eq!(interpreter.step(), Err(IntcodeError::Overflow { ip: 4 }));
+        assert_eq!(interpreter.relative_base, Word::MAX);
+    }
+
+    #[test]
+    fn load_at_patches_code() {
+        // Jumps past the end of the program, where the overlay goes.
+        let mut interpreter = Interpreter::new(&[1105, 1, 10]);
+        interpreter.echo_output = false;
+        interpreter.load_at(10, &[104, 42, 99]);
+        assert_eq!(interpreter.read(10), 104);
+        assert_eq!(interpreter.read(12), 99);
+
+        interpreter.run().unwrap();
+        assert_eq!(interpreter.pop_output(), Some(42));
+    }
+
+    #[test]
+    fn immediate_write_is_an_error() {
+        let mut interpreter = Interpreter::new(&[104, 0, 11101, 1, 1, 0, 99]);
+        interpreter.echo_output = false;
+        assert_eq!(interpreter.step(), Ok(true));
+        assert_eq!(
+            interpreter.step(),
+            Err(IntcodeError::ImmediateWrite { ip: 2 })
+        );
+        assert_eq!(interpreter.memory(), &vec![104, 0, 11101, 1, 1, 0, 99]);
+    }
+
+    #[test]
+    fn queued_inputs_read_in_order() {
+        let mut interpreter = Interpreter::new(&[3, 13, 4, 13, 3, 13, 4, 13, 3, 13, 4, 13, 99]);
+        interpreter.echo_output = false;
+        for input in &[-1, 0, 1] {
+            interpreter.push_input(*input);
+        }
+        assert_eq!(interpreter.run_collecting(usize::MAX), Ok(vec![-1, 0, 1]));
+        assert!(interpreter.inputs.is_empty());
+    }
+
+    #[test]
+    fn read_writes_to_first_parameter() {
+        let mut interpreter = Interpreter::new(&[3, 1, 99]);
+        interpreter.push_input(99);
+        interpreter.run().unwrap();
+        assert_eq!(interpreter.memory(), &vec![3, 99, 99]);
+
+        // In relative mode, with nothing after the read that could be mistaken
+        // for a third parameter.
+        let mut interpreter = Interpreter::new(&[109, 6, 203, -1, 99, 0]);
+        interpreter.push_input(7);
+        interpreter.run().unwrap();
+        assert_eq!(interpreter.memory(), &vec![109, 6, 203, -1, 99, 7]);
+    }
+
+    #[test]
+    fn negative_relative_address() {
+        let mut interpreter = Interpreter::new(&[109, -10, 21101, 1, 1, 3, 99]);
+        assert_eq!(
+            interpreter.run(),
+            Err(IntcodeError::NegativeAddress { address: -7, ip: 2 })
+        );
+
+        let mut interpreter = Interpreter::new(&[109, -10, 204, 3, 99]);
+        assert_eq!(
+            interpreter.run(),
+            Err(IntcodeError::NegativeAddress { address: -7, ip: 2 })
+        );
+    }
+
+    #[test]
+    fn run_until_input_pauses_on_read() {
+        let mut interpreter = Interpreter::new(&[3, 0, 4, 0, 99]);
+        assert_eq!(interpreter.run_until_input(), Ok(StepResult::NeedInput));
+        assert_eq!(interpreter.ip, 0);
+
+        interpreter.push_input(9);
+        assert_eq!(interpreter.run_until_input(), Ok(StepResult::Output(9)));
+        assert_eq!(interpreter.run_until_input(), Ok(StepResult::Halted));
+    }
+
+    #[test]
+    fn run_until_input_reports_errors() {
+        let mut interpreter = Interpreter::new(&[1105, 1, 100]);
+        assert_eq!(
+            interpreter.run_until_input(),
+            Err(IntcodeError::UnmappedIp { ip: 100 })
+        );
+    }
+
+    #[test]
+    fn run_collecting_stops_at_limit() {
+        // Counts up forever, outputting every number.
+        let mut interpreter = Interpreter::new(&[1001, 9, 1, 9, 4, 9, 1105, 1, 0, 0]);
+        interpreter.echo_output = false;
+        assert_eq!(interpreter.run_collecting(3), Ok(vec![1, 2, 3]));
+        assert_eq!(interpreter.run_collecting(2), Ok(vec![4, 5]));
+
+        let mut interpreter = Interpreter::new(&[104, 1, 99]);
+        interpreter.echo_output = false;
+        assert_eq!(interpreter.run_collecting(3), Ok(vec![1]));
+    }
+
+    #[test]
+    fn read_memory() {
+        let interpreter = Interpreter::new(&[1, 2, 3]);
+        assert_eq!(interpreter.read(2), 3);
+        assert_eq!(interpreter.read(3), 0);
+        assert_eq!(interpreter.read(1_000_000), 0);
+        assert_eq!(interpreter.memory(), &vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn receive_waits_for_rx() {
+        let (tx, rx) = channel();
+        let mut interpreter = Interpreter::new(&[3, 0, 4, 0, 99]);
+        interpreter.rx = Some(rx);
+        interpreter.echo_output = false;
+
+        let sender = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            tx.send(42).unwrap();
+        });
+        interpreter.run().unwrap();
+        sender.join().unwrap();
+        assert_eq!(interpreter.pop_output(), Some(42));
+    }
+
+    #[test]
+    fn pipe_between_interpreters() {
+        let (tx, rx) = channel();
+        let (tx_b, rx_b) = channel();
+
+        let mut a = Interpreter::new(&[104, 5, 99]);
+        a.tx = Some(tx);
+        let mut b = Interpreter::new(&[3, 0, 102, 2, 0, 0, 4, 0, 99]);
+        b.rx = Some(rx);
+        b.tx = Some(tx_b);
+
+        let a = thread::spawn(move || a.run());
+        let b = thread::spawn(move || b.run());
+        a.join().unwrap().unwrap();
+        b.join().unwrap().unwrap();
+        assert_eq!(rx_b.iter().collect::<Vec<_>>(), vec![10]);
+    }
+
+    #[test]
+    fn receive_from_dropped_rx() {
+        let (tx, rx) = channel::<Word>();
+        drop(tx);
+        let mut interpreter = Interpreter::new(&[3, 0, 99]);
+        interpreter.rx = Some(rx);
+        assert_eq!(interpreter.run(), Err(IntcodeError::Disconnected { ip: 0 }));
+    }
+}
@@ -0,0 +1,59 @@
+use crate::intcode::interpreter::IntcodeError;
+use crate::intcode::Word;
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepResult {
+    Output(Word),
+    NeedInput,
+    Halted,
+}
+
+// Something that runs an Intcode program, so drivers don't have to care
+// whether they're talking to a real interpreter.
+pub trait Machine {
+    // Runs until the program outputs a value. None once it has halted, or when it
+    // wants input it hasn't been given yet.
+    fn next_output(&mut self) -> Result<Option<Word>, IntcodeError>;
+    fn provide_input(&mut self, value: Word);
+    fn is_halted(&self) -> bool;
+}
+
+// Plays back a fixed script instead of running a program. Whatever input it's
+// given ends up in `inputs`.
+pub struct ScriptedMachine {
+    script: VecDeque<StepResult>,
+    halted: bool,
+    pub inputs: Vec<Word>,
+}
+
+impl ScriptedMachine {
+    pub fn new(script: Vec<StepResult>) -> Self {
+        Self {
+            script: script.into(),
+            halted: false,
+            inputs: Vec::new(),
+        }
+    }
+}
+
+impl Machine for ScriptedMachine {
+    fn next_output(&mut self) -> Result<Option<Word>, IntcodeError> {
+        match self.script.pop_front().unwrap_or(StepResult::Halted) {
+            StepResult::Output(value) => Ok(Some(value)),
+            StepResult::NeedInput => Ok(None),
+            StepResult::Halted => {
+                self.halted = true;
+                Ok(None)
+            }
+        }
+    }
+
+    fn provide_input(&mut self, value: Word) {
+        self.inputs.push(value);
+    }
+
+    fn is_halted(&self) -> bool {
+        self.halted
+    }
+}
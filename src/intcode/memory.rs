@@ -0,0 +1,96 @@
+use crate::intcode::Word;
+use std::collections::HashMap;
+
+// Where an interpreter keeps its program and everything it writes. Addresses
+// that were never written read as None.
+pub trait Memory: Clone {
+    fn load(program: &[Word]) -> Self;
+    fn get(&self, address: usize) -> Option<Word>;
+    // Makes room for `address` first if there isn't any yet.
+    fn get_mut(&mut self, address: usize) -> &mut Word;
+    // How many addresses are backed by storage.
+    fn size(&self) -> usize;
+}
+
+// Grows to fit the highest address written, zeroing everything in between.
+impl Memory for Vec<Word> {
+    fn load(program: &[Word]) -> Self {
+        program.to_vec()
+    }
+
+    fn get(&self, address: usize) -> Option<Word> {
+        self.as_slice().get(address).copied()
+    }
+
+    fn get_mut(&mut self, address: usize) -> &mut Word {
+        if address >= self.len() {
+            self.resize(address + 1, 0);
+        }
+        &mut self[address]
+    }
+
+    fn size(&self) -> usize {
+        self.len()
+    }
+}
+
+// Only stores the addresses that are actually used, for programs that write far
+// past their own end.
+#[derive(Clone, Default)]
+pub struct SparseMemory(HashMap<usize, Word>);
+
+impl Memory for SparseMemory {
+    fn load(program: &[Word]) -> Self {
+        SparseMemory(program.iter().copied().enumerate().collect())
+    }
+
+    fn get(&self, address: usize) -> Option<Word> {
+        self.0.get(&address).copied()
+    }
+
+    fn get_mut(&mut self, address: usize) -> &mut Word {
+        self.0.entry(address).or_insert(0)
+    }
+
+    fn size(&self) -> usize {
+        self.0.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::interpreter::Interpreter;
+
+    // Adds two numbers into `address`, and outputs what's there.
+    fn far_write(address: Word) -> Vec<Word> {
+        vec![1101, 20, 22, address, 4, address, 99]
+    }
+
+    fn run<M: Memory>(mut interpreter: Interpreter<M>) -> (Word, usize) {
+        interpreter.echo_output = false;
+        interpreter.run().unwrap();
+        (
+            interpreter.pop_output().unwrap(),
+            interpreter.memory().size(),
+        )
+    }
+
+    #[test]
+    fn sparse_and_dense_agree() {
+        let program = far_write(1000);
+        assert_eq!(run(Interpreter::new(&program)), (42, 1001));
+        assert_eq!(run(Interpreter::sparse(&program)), (42, 8));
+    }
+
+    #[test]
+    fn sparse_far_addresses() {
+        let program = far_write(1 << 40);
+        assert_eq!(run(Interpreter::sparse(&program)), (42, 8));
+
+        let mut memory = SparseMemory::load(&program);
+        assert_eq!(memory.get(1 << 41), None);
+        *memory.get_mut(1 << 41) += 5;
+        assert_eq!(memory.get(1 << 41), Some(5));
+    }
+}
@@ -0,0 +1,131 @@
+pub mod interpreter;
+pub mod machine;
+pub mod memory;
+pub mod network;
+
+use std::fmt::{Display, Formatter};
+use std::io::{self, Read};
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+// The integer type Intcode programs compute with. Changing the width here
+// changes it for every interpreter built on the library.
+pub type Word = i64;
+
+#[derive(Debug)]
+pub enum ParseError {
+    Io(io::Error),
+    Int(ParseIntError),
+}
+
+impl From<io::Error> for ParseError {
+    fn from(error: io::Error) -> Self {
+        ParseError::Io(error)
+    }
+}
+
+impl From<ParseIntError> for ParseError {
+    fn from(error: ParseIntError) -> Self {
+        ParseError::Int(error)
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            ParseError::Io(error) => write!(formatter, "couldn't read program: {}", error),
+            ParseError::Int(error) => write!(formatter, "invalid program: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntcodeProgram(Vec<Word>);
+
+impl IntcodeProgram {
+    pub fn from_reader(mut reader: impl Read) -> Result<Self, ParseError> {
+        let mut input = String::new();
+        reader.read_to_string(&mut input)?;
+        input.parse()
+    }
+}
+
+impl FromStr for IntcodeProgram {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let memory = input
+            .trim()
+            .split(',')
+            .map(|x| x.trim().parse())
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self(memory))
+    }
+}
+
+impl From<IntcodeProgram> for Vec<Word> {
+    fn from(program: IntcodeProgram) -> Self {
+        program.0
+    }
+}
+
+impl From<Vec<Word>> for IntcodeProgram {
+    fn from(memory: Vec<Word>) -> Self {
+        Self(memory)
+    }
+}
+
+// The same comma separated form programs are read from.
+impl Display for IntcodeProgram {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        write!(formatter, "{}", serialize_program(&self.0))
+    }
+}
+
+pub fn serialize_program(program: &[Word]) -> String {
+    let words: Vec<String> = program.iter().map(Word::to_string).collect();
+    words.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_program() {
+        let program: IntcodeProgram = "1,9,10,3,\n2,3,11,0,99,30,40,50\n".parse().unwrap();
+        assert_eq!(
+            Vec::from(program),
+            vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]
+        );
+
+        let program = IntcodeProgram::from_reader("104,-1,99".as_bytes()).unwrap();
+        assert_eq!(Vec::from(program), vec![104, -1, 99]);
+    }
+
+    #[test]
+    fn serialize_round_trip() {
+        let text = include_str!("../../09/input.in");
+        let memory: Vec<Word> = text.parse::<IntcodeProgram>().unwrap().into();
+        let serialized = serialize_program(&memory);
+        assert_eq!(serialized, text.trim());
+
+        let mut patched = memory.clone();
+        patched[0] = 2;
+        let program: IntcodeProgram = serialize_program(&patched).parse().unwrap();
+        assert_eq!(Vec::from(program.clone()), patched);
+        assert_eq!(program.to_string(), serialize_program(&patched));
+        assert_eq!(serialize_program(&[]), "");
+    }
+
+    #[test]
+    fn parse_empty_word() {
+        assert!(matches!(
+            "1,,2".parse::<IntcodeProgram>(),
+            Err(ParseError::Int(_))
+        ));
+    }
+}
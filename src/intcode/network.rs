@@ -0,0 +1,108 @@
+use crate::intcode::interpreter::{IntcodeError, Interpreter};
+use crate::intcode::machine::{Machine, StepResult};
+use std::fmt::{Debug, Display, Formatter};
+
+pub enum NetworkError {
+    // Every machine that hasn't halted is waiting for input, and some of them
+    // are waiting on each other.
+    Deadlock,
+    Intcode(IntcodeError),
+}
+
+impl From<IntcodeError> for NetworkError {
+    fn from(error: IntcodeError) -> Self {
+        NetworkError::Intcode(error)
+    }
+}
+
+impl Display for NetworkError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            NetworkError::Deadlock => write!(formatter, "machines are waiting on each other"),
+            NetworkError::Intcode(error) => write!(formatter, "{}", error),
+        }
+    }
+}
+
+impl Debug for NetworkError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        Display::fmt(self, formatter)
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+// Runs the machines in turn on a single thread, each until it blocks on input,
+// until they've all halted. Whatever machine `i` outputs is given to machine
+// `wiring(i)`. Machines left waiting only on halted ones will never get any more
+// input, so that counts as finished too. Returns the machines, so their
+// leftover inputs can be looked at.
+pub fn run_network(
+    mut machines: Vec<Interpreter>,
+    wiring: impl Fn(usize) -> usize,
+) -> Result<Vec<Interpreter>, NetworkError> {
+    for machine in machines.iter_mut() {
+        machine.echo_output = false;
+    }
+
+    while machines.iter().any(|machine| !machine.is_halted()) {
+        let mut progress = false;
+        for i in 0..machines.len() {
+            if machines[i].is_halted() {
+                continue;
+            }
+
+            // A machine can loop back to the same Read after getting somewhere,
+            // so progress is anything it ran, not where it ended up.
+            let cycles = machines[i].cycles;
+            let to = wiring(i);
+            while let StepResult::Output(value) = machines[i].run_until_input()? {
+                machines[to].push_input(value);
+            }
+            progress |= machines[i].is_halted() || machines[i].cycles != cycles;
+        }
+
+        if !progress {
+            let waiting = |i: usize| !machines[i].is_halted();
+            let starved = (0..machines.len())
+                .filter(|&i| waiting(i))
+                .all(|i| (0..machines.len()).all(|j| wiring(j) != i || !waiting(j)));
+            if starved {
+                break;
+            }
+            return Err(NetworkError::Deadlock);
+        }
+    }
+
+    Ok(machines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reads a value, passes it on plus one, and halts.
+    const INCREMENT: &[i64] = &[3, 9, 1001, 9, 1, 9, 4, 9, 99, 0];
+
+    fn ring() -> Vec<Interpreter> {
+        (0..3).map(|_| Interpreter::new(INCREMENT)).collect()
+    }
+
+    #[test]
+    fn ring_of_three() {
+        let mut machines = ring();
+        machines[0].push_input(10);
+
+        let machines = run_network(machines, |i| (i + 1) % 3).unwrap();
+        assert!(machines.iter().all(|machine| machine.is_halted()));
+        assert_eq!(machines[0].inputs, vec![13]);
+    }
+
+    #[test]
+    fn waiting_on_each_other() {
+        assert!(matches!(
+            run_network(ring(), |i| (i + 1) % 3),
+            Err(NetworkError::Deadlock)
+        ));
+    }
+}
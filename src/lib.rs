@@ -0,0 +1,8 @@
+pub mod combinatorics;
+pub mod hash;
+pub mod input;
+pub mod intcode;
+pub mod map;
+pub mod math;
+pub mod ocr;
+pub mod output;
@@ -0,0 +1,38 @@
+pub trait Renderable {
+    fn glyph(&self) -> &str;
+}
+
+pub fn render_grid<T: Renderable>(grid: &[Vec<T>]) -> String {
+    let mut output = String::new();
+    for row in grid {
+        for cell in row {
+            output += cell.glyph();
+        }
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl Renderable for bool {
+        fn glyph(&self) -> &str {
+            if *self {
+                "#"
+            } else {
+                "."
+            }
+        }
+    }
+
+    #[test]
+    fn render_rows() {
+        assert_eq!(
+            render_grid(&[vec![true, false], vec![false, true]]),
+            "#.\n.#\n"
+        );
+        assert_eq!(render_grid::<bool>(&[]), "");
+    }
+}
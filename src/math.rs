@@ -0,0 +1,67 @@
+use std::convert::TryFrom;
+
+pub fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// Divides a direction by the gcd of its components, keeping their signs, so all
+// multiples of a direction reduce to the same one. (0, 0) stays as it is.
+pub fn reduce(dx: i64, dy: i64) -> (i64, i64) {
+    // The gcd can be 2^63, which only fits an i64 once divided into a component.
+    let divisor = i128::from(gcd(dx.unsigned_abs(), dy.unsigned_abs()));
+    if divisor == 0 {
+        (0, 0)
+    } else {
+        let divide = |component: i64| (i128::from(component) / divisor) as i64;
+        (divide(dx), divide(dy))
+    }
+}
+
+// Least common multiple of all values, or None if it doesn't fit in a u64.
+pub fn lcm_all(values: &[u64]) -> Option<u64> {
+    values.iter().try_fold(1, |lcm, &value| {
+        if lcm == 0 || value == 0 {
+            return Some(0);
+        }
+
+        let lcm = u128::from(lcm) * u128::from(value / gcd(lcm, value));
+        u64::try_from(lcm).ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reduce_directions() {
+        assert_eq!(reduce(4, 2), (2, 1));
+        assert_eq!(reduce(0, -3), (0, -1));
+        assert_eq!(reduce(-6, 9), (-2, 3));
+        assert_eq!(reduce(5, 0), (1, 0));
+        assert_eq!(reduce(0, 0), (0, 0));
+        assert_eq!(reduce(i64::MIN, 0), (-1, 0));
+    }
+
+    #[test]
+    fn gcd_with_zero() {
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(0, 7), 7);
+        assert_eq!(gcd(0, 0), 0);
+    }
+
+    #[test]
+    fn lcm_of_periods() {
+        assert_eq!(lcm_all(&[18, 28, 44]), Some(2772));
+        assert_eq!(lcm_all(&[]), Some(1));
+    }
+
+    #[test]
+    fn lcm_overflow() {
+        assert_eq!(lcm_all(&[u64::MAX, u64::MAX - 1]), None);
+    }
+}
@@ -0,0 +1,86 @@
+// The letters AoC draws with lit pixels, four columns wide and six rows tall,
+// with a blank column between letters.
+const GLYPHS: &[(char, [&str; 6])] = &[
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', ["#...", "#...", ".#.#", "..#.", "..#.", "..#."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+fn glyph(grid: &[Vec<bool>], left: usize) -> char {
+    let lit = |y: usize, x: usize| grid[y].get(left + x).copied().unwrap_or(false);
+    GLYPHS
+        .iter()
+        .find(|(_, rows)| {
+            rows.iter().enumerate().all(|(y, row)| {
+                row.chars()
+                    .enumerate()
+                    .all(|(x, pixel)| (pixel == '#') == lit(y, x))
+            })
+        })
+        .map_or('?', |(letter, _)| *letter)
+}
+
+// Reads the letters in a six row grid of lit pixels. Blank columns on the left
+// are skipped, and anything that isn't a known letter comes out as `?`.
+pub fn ocr(grid: &[Vec<bool>]) -> String {
+    if grid.len() != 6 {
+        return String::new();
+    }
+
+    let width = grid.iter().map(|row| row.len()).max().unwrap_or(0);
+    let blank = |x: usize| grid.iter().all(|row| !row.get(x).copied().unwrap_or(false));
+    let mut left = (0..width).find(|x| !blank(*x)).unwrap_or(width);
+
+    let mut letters = String::new();
+    while (left..width).any(|x| !blank(x)) {
+        letters.push(glyph(grid, left));
+        left += 5;
+    }
+    letters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(rows: &[&str]) -> Vec<Vec<bool>> {
+        rows.iter()
+            .map(|row| row.chars().map(|pixel| pixel == '#').collect())
+            .collect()
+    }
+
+    #[test]
+    fn reads_letters() {
+        let letters = grid(&[
+            "...#..#.####.",
+            "...#..#.#....",
+            "...####.###..",
+            "...#..#.#....",
+            "...#..#.#....",
+            "...#..#.####.",
+        ]);
+        assert_eq!(ocr(&letters), "HE");
+    }
+
+    #[test]
+    fn unknown_letters() {
+        let letters = grid(&["#.#.", ".#..", "#.#.", ".#..", "#.#.", ".#.."]);
+        assert_eq!(ocr(&letters), "?");
+        assert_eq!(ocr(&letters[..5]), "");
+    }
+}
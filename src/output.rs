@@ -0,0 +1,55 @@
+use std::fmt::Display;
+use std::io::IsTerminal;
+
+// Quiet mode hides everything but the answers. It's turned on by passing
+// `--quiet` or by setting AOC_QUIET.
+pub fn quiet() -> bool {
+    std::env::var_os("AOC_QUIET").is_some() || std::env::args().any(|arg| arg == "--quiet")
+}
+
+// The program's arguments, minus `--quiet`.
+pub fn args() -> impl Iterator<Item = String> {
+    std::env::args().filter(|arg| arg != "--quiet")
+}
+
+// Whether to draw with ANSI cursor movement. On when stdout is a terminal; set
+// AOC_ANSI to 1 or 0 to force it either way.
+pub fn ansi() -> bool {
+    match std::env::var("AOC_ANSI") {
+        Ok(value) => value != "0",
+        Err(_) => std::io::stdout().is_terminal(),
+    }
+}
+
+// Moves the cursor to the top left, or nothing when ANSI is off so frames just
+// follow each other.
+pub fn home() -> &'static str {
+    if ansi() {
+        "\x1B[1;1H"
+    } else {
+        ""
+    }
+}
+
+pub fn answer(part: u8, value: impl Display) {
+    println!("Part{}: {}", part, value);
+}
+
+// print! and println!, except in quiet mode.
+#[macro_export]
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if !$crate::output::quiet() {
+            print!($($arg)*);
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! sayln {
+    ($($arg:tt)*) => {
+        if !$crate::output::quiet() {
+            println!($($arg)*);
+        }
+    };
+}
@@ -0,0 +1,40 @@
+use std::process::Command;
+
+macro_rules! input {
+    ($path:literal) => {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/", $path)
+    };
+}
+
+// Piped output isn't a terminal, so the drawing days leave out cursor movement.
+#[test]
+fn no_escapes_when_piped() {
+    let days: &[(&str, &[&str])] = &[
+        (env!("CARGO_BIN_EXE_11"), &[input!("11/input.in")]),
+        (env!("CARGO_BIN_EXE_13"), &[input!("13/input")]),
+        (env!("CARGO_BIN_EXE_15"), &[input!("15/input"), "--animate"]),
+    ];
+    for (day, args) in days {
+        let output = Command::new(day)
+            .args(*args)
+            .env_remove("AOC_ANSI")
+            .env_remove("AOC_QUIET")
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{} failed", day);
+        assert!(output.stdout.len() > 1000, "{} drew nothing", day);
+        assert!(!output.stdout.contains(&0x1B), "{} wrote an escape", day);
+    }
+}
+
+#[test]
+fn escapes_when_forced() {
+    let output = Command::new(env!("CARGO_BIN_EXE_11"))
+        .arg(input!("11/input.in"))
+        .env("AOC_ANSI", "1")
+        .env_remove("AOC_QUIET")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(output.stdout.contains(&0x1B));
+}
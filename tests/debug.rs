@@ -0,0 +1,27 @@
+use std::fs;
+use std::process::Command;
+
+const QUINE: &str = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99\n";
+
+fn day09(debug: bool) -> String {
+    let program =
+        std::env::temp_dir().join(format!("aoc2019-debug-{}-{}", std::process::id(), debug));
+    fs::write(&program, QUINE).unwrap();
+
+    let mut command = Command::new(env!("CARGO_BIN_EXE_09"));
+    command.arg(&program).env_remove("AOC_QUIET");
+    if debug {
+        command.arg("--debug");
+    }
+    let output = command.output().unwrap();
+    fs::remove_file(&program).unwrap();
+
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn trace_is_opt_in() {
+    assert!(!day09(false).contains("ip="));
+    assert!(day09(true).contains("ip=2     rb=1     | RelativeBase 1"));
+}
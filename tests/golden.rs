@@ -0,0 +1,135 @@
+use std::fs::File;
+use std::process::{Command, Stdio};
+
+// How a day gets its puzzle input.
+enum Input {
+    Stdin(&'static str),
+    Argument(&'static str),
+}
+
+struct Golden {
+    binary: &'static str,
+    input: Input,
+    answers: [&'static str; 2],
+}
+
+macro_rules! input {
+    ($path:literal) => {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/", $path)
+    };
+}
+
+// The answers each day gives for its committed input.
+const GOLDEN: &[Golden] = &[
+    Golden {
+        binary: env!("CARGO_BIN_EXE_01"),
+        input: Input::Stdin(input!("01/input")),
+        answers: ["3369286", "5051054"],
+    },
+    Golden {
+        binary: env!("CARGO_BIN_EXE_02"),
+        input: Input::Stdin(input!("02/input")),
+        answers: ["3101844", "8478"],
+    },
+    Golden {
+        binary: env!("CARGO_BIN_EXE_03"),
+        input: Input::Stdin(input!("03/input")),
+        answers: ["1264", "37390"],
+    },
+    Golden {
+        binary: env!("CARGO_BIN_EXE_04"),
+        input: Input::Argument(input!("04/input")),
+        answers: ["2919", "2046"],
+    },
+    Golden {
+        binary: env!("CARGO_BIN_EXE_05"),
+        input: Input::Argument(input!("05/input")),
+        answers: ["13346482", "12111395"],
+    },
+    Golden {
+        binary: env!("CARGO_BIN_EXE_06"),
+        input: Input::Argument(input!("06/input")),
+        answers: ["106065", "253"],
+    },
+    Golden {
+        binary: env!("CARGO_BIN_EXE_07"),
+        input: Input::Argument(input!("07/input.in")),
+        answers: ["34852", "44282086"],
+    },
+    Golden {
+        binary: env!("CARGO_BIN_EXE_08"),
+        input: Input::Stdin(input!("08/input.in")),
+        answers: ["1703", "HCGFE"],
+    },
+    Golden {
+        binary: env!("CARGO_BIN_EXE_09"),
+        input: Input::Argument(input!("09/input.in")),
+        answers: ["2316632620", "78869"],
+    },
+    // There's no real input for day 10 in the repo, so this is the puzzle's
+    // largest example.
+    Golden {
+        binary: env!("CARGO_BIN_EXE_10"),
+        input: Input::Argument(input!("10/example5.in")),
+        answers: ["210", "802"],
+    },
+    Golden {
+        binary: env!("CARGO_BIN_EXE_11"),
+        input: Input::Argument(input!("11/input.in")),
+        answers: ["1951", "HKJBAHCR"],
+    },
+    Golden {
+        binary: env!("CARGO_BIN_EXE_12"),
+        input: Input::Stdin(input!("12/input.in")),
+        answers: ["12773", "306798770391636"],
+    },
+    Golden {
+        binary: env!("CARGO_BIN_EXE_13"),
+        input: Input::Argument(input!("13/input")),
+        answers: ["324", "15957"],
+    },
+    Golden {
+        binary: env!("CARGO_BIN_EXE_14"),
+        input: Input::Stdin(input!("14/input")),
+        answers: ["612880", "2509120"],
+    },
+    Golden {
+        binary: env!("CARGO_BIN_EXE_15"),
+        input: Input::Argument(input!("15/input")),
+        answers: ["240", "322"],
+    },
+    Golden {
+        binary: env!("CARGO_BIN_EXE_16"),
+        input: Input::Argument(input!("16/input")),
+        answers: ["29795507", "89568529"],
+    },
+];
+
+// Runs a day in quiet mode, which leaves only its answers on stdout.
+fn run(golden: &Golden) -> String {
+    let mut command = Command::new(golden.binary);
+    command.arg("--quiet").stdin(Stdio::null());
+    match golden.input {
+        Input::Stdin(path) => {
+            command.stdin(File::open(path).unwrap());
+        }
+        Input::Argument(path) => {
+            command.arg(path);
+        }
+    }
+
+    let output = command.output().unwrap();
+    assert!(output.status.success(), "{} failed", golden.binary);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn golden_answers() {
+    for golden in GOLDEN {
+        let expected = format!(
+            "Part1: {}\nPart2: {}\n",
+            golden.answers[0], golden.answers[1]
+        );
+        assert_eq!(run(golden), expected, "{}", golden.binary);
+    }
+}
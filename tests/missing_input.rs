@@ -0,0 +1,39 @@
+use std::process::Command;
+
+const DAYS: &[&str] = &[
+    env!("CARGO_BIN_EXE_01"),
+    env!("CARGO_BIN_EXE_02"),
+    env!("CARGO_BIN_EXE_03"),
+    env!("CARGO_BIN_EXE_04"),
+    env!("CARGO_BIN_EXE_05"),
+    env!("CARGO_BIN_EXE_06"),
+    env!("CARGO_BIN_EXE_07"),
+    env!("CARGO_BIN_EXE_08"),
+    env!("CARGO_BIN_EXE_09"),
+    env!("CARGO_BIN_EXE_10"),
+    env!("CARGO_BIN_EXE_11"),
+    env!("CARGO_BIN_EXE_12"),
+    env!("CARGO_BIN_EXE_13"),
+    env!("CARGO_BIN_EXE_14"),
+    env!("CARGO_BIN_EXE_15"),
+    env!("CARGO_BIN_EXE_16"),
+];
+
+// Every day takes its input as a path, and one that doesn't exist is reported
+// rather than panicking.
+#[test]
+fn nonexistent_path() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/nonexistent/input");
+    for day in DAYS {
+        let output = Command::new(day).arg(path).output().unwrap();
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(!output.status.success(), "{} succeeded", day);
+        assert!(
+            stderr.starts_with(&format!("Error: could not open input: {}: ", path)),
+            "{}: {}",
+            day,
+            stderr
+        );
+        assert!(!stderr.contains("panicked"), "{}: {}", day, stderr);
+    }
+}
@@ -0,0 +1,33 @@
+use std::process::{Command, Output};
+
+fn day06(quiet: impl FnOnce(&mut Command) -> &mut Command) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_06"));
+    command
+        .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/06/input"))
+        .env_remove("AOC_QUIET");
+    let output = quiet(&mut command).output().unwrap();
+    assert!(output.status.success());
+    output
+}
+
+const ANSWERS: &str = "Part1: 106065\nPart2: 253\n";
+
+#[test]
+fn loud_by_default() {
+    let output = day06(|command| command);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with(ANSWERS));
+    assert_ne!(stdout, ANSWERS);
+}
+
+#[test]
+fn quiet_flag_prints_only_answers() {
+    let output = day06(|command| command.arg("--quiet"));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), ANSWERS);
+}
+
+#[test]
+fn quiet_env_prints_only_answers() {
+    let output = day06(|command| command.env("AOC_QUIET", "1"));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), ANSWERS);
+}
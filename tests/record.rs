@@ -0,0 +1,54 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+// Adds up the two numbers it reads.
+const ADDER: &str = "3,100,3,101,1,100,101,102,4,102,99\n";
+
+fn scratch(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("aoc2019-record-{}-{}", std::process::id(), name))
+}
+
+fn day09(args: &[&std::path::Path], stdin: Stdio) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_09"))
+        .args(args)
+        .stdin(stdin)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn recorded_session_replays() {
+    let program = scratch("program");
+    let inputs = scratch("inputs");
+    fs::write(&program, ADDER).unwrap();
+    fs::write(scratch("typed"), "3\n4\n").unwrap();
+
+    let typed = fs::File::open(scratch("typed")).unwrap();
+    let recorded = day09(
+        &[
+            program.as_path(),
+            "--record-inputs".as_ref(),
+            inputs.as_path(),
+        ],
+        typed.into(),
+    );
+    assert_eq!(fs::read_to_string(&inputs).unwrap(), "3\n4\n");
+
+    let replayed = day09(
+        &[
+            program.as_path(),
+            "--replay-inputs".as_ref(),
+            inputs.as_path(),
+        ],
+        Stdio::null(),
+    );
+    assert!(recorded.ends_with("outputs: [7]\n"), "{}", recorded);
+    assert_eq!(replayed, recorded);
+
+    for name in &["program", "inputs", "typed"] {
+        fs::remove_file(scratch(name)).unwrap();
+    }
+}